@@ -0,0 +1,21 @@
+CREATE TABLE tags_old (
+    file_id INTEGER,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (file_id, tag),
+    FOREIGN KEY (file_id) REFERENCES files(id)
+);
+INSERT INTO tags_old (file_id, tag) SELECT file_id, tag FROM tags;
+DROP TABLE tags;
+ALTER TABLE tags_old RENAME TO tags;
+
+CREATE TABLE attributes_old (
+    file_id INTEGER,
+    attr_key TEXT NOT NULL,
+    attr_value TEXT NOT NULL,
+    PRIMARY KEY (file_id, attr_key, attr_value),
+    FOREIGN KEY (file_id) REFERENCES files(id)
+);
+INSERT INTO attributes_old (file_id, attr_key, attr_value)
+    SELECT file_id, attr_key, attr_value FROM attributes;
+DROP TABLE attributes;
+ALTER TABLE attributes_old RENAME TO attributes;
@@ -0,0 +1,25 @@
+-- Remove rows left behind by files that have been removed
+DELETE FROM tags WHERE file_id IS NULL OR file_id NOT IN (SELECT id FROM files);
+DELETE FROM attributes WHERE file_id IS NULL OR file_id NOT IN (SELECT id FROM files);
+
+CREATE TABLE tags_new (
+    file_id INTEGER NOT NULL,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (file_id, tag),
+    FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+);
+INSERT INTO tags_new (file_id, tag) SELECT file_id, tag FROM tags;
+DROP TABLE tags;
+ALTER TABLE tags_new RENAME TO tags;
+
+CREATE TABLE attributes_new (
+    file_id INTEGER NOT NULL,
+    attr_key TEXT NOT NULL,
+    attr_value TEXT NOT NULL,
+    PRIMARY KEY (file_id, attr_key, attr_value),
+    FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+);
+INSERT INTO attributes_new (file_id, attr_key, attr_value)
+    SELECT file_id, attr_key, attr_value FROM attributes;
+DROP TABLE attributes;
+ALTER TABLE attributes_new RENAME TO attributes;
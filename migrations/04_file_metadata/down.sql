@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS file_hashes;
+ALTER TABLE files DROP COLUMN mtime;
+ALTER TABLE files DROP COLUMN size;
+ALTER TABLE files DROP COLUMN hash;
@@ -0,0 +1,4 @@
+ALTER TABLE files ADD COLUMN hash TEXT;
+ALTER TABLE files ADD COLUMN size BIGINT;
+ALTER TABLE files ADD COLUMN mtime BIGINT;
+CREATE INDEX file_hashes ON files(hash);
@@ -0,0 +1,2 @@
+ALTER TABLE files ADD COLUMN device BIGINT;
+ALTER TABLE files ADD COLUMN inode BIGINT;
@@ -0,0 +1,6 @@
+CREATE TABLE trash (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    path TEXT NOT NULL,
+    trash_name TEXT NOT NULL,
+    files TEXT NOT NULL
+);
@@ -0,0 +1,3 @@
+DROP INDEX journal_change_paths;
+DROP TABLE journal_changes;
+DROP TABLE journal;
@@ -0,0 +1,18 @@
+CREATE TABLE journal (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    operation TEXT NOT NULL,
+    timestamp BIGINT NOT NULL,
+    undone BOOLEAN NOT NULL DEFAULT 0
+);
+
+CREATE TABLE journal_changes (
+    journal_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    path TEXT NOT NULL,
+    new_path TEXT,
+    change TEXT NOT NULL,
+    PRIMARY KEY (journal_id, position),
+    FOREIGN KEY (journal_id) REFERENCES journal(id) ON DELETE CASCADE
+);
+
+CREATE INDEX journal_change_paths ON journal_changes(path);
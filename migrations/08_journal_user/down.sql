@@ -0,0 +1 @@
+ALTER TABLE journal DROP COLUMN user;
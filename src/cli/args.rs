@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, str::FromStr};
 use structopt::StructOpt;
 use crate::utils::files::{FileSearchSettings, FiletypeFilter};
 
@@ -16,18 +16,55 @@ pub struct Arguments {
 pub enum Command {
     /// Add tag to files
     Add(TaggingArgs),
+    /// Check database for inconsistencies
+    Check(CheckArgs),
     /// Remove files that does not exist from database
-    Cleanup,
+    Cleanup(CleanupArgs),
+    /// Remove all tags and attributes from files
+    Clear(ClearArgs),
+    /// Copy file or directory together with its tags
+    Cp(CopyArgs),
+    /// Export all files with their tags and attributes
+    Export(ExportArgs),
+    /// Add metadata embedded in audio, image and pdf files, or in file names, as attributes
+    Extract(ExtractArgs),
+    /// Remove files from database without removing them from disk
+    Forget(ForgetArgs),
+    /// List operations that changed the database
+    History(HistoryArgs),
+    /// Import files with their tags and attributes from a file created by export or a TMSU database
+    Import(ImportArgs),
     /// Print information about file
     Info(InfoArgs),
     /// Initialize new database in current directory
     Init,
+    /// Show when tags and attributes were added to or removed from a file
+    Log(LogArgs),
+    /// Move file or directory together with its tags
+    Mv(MoveArgs),
+    /// Move files into directories created from a template and their attributes
+    Organize(OrganizeArgs),
     /// Remove tag from files
-    Remove(TaggingArgs),
+    Remove(RemoveArgs),
     /// Rename files
     Rename(RenameArgs),
+    /// Move files back from trash and restore their tags. Lists trashed files if no files are given
+    Restore(RestoreArgs),
+    /// Delete files from disk and database
+    Rm(RmArgs),
     /// Search in database
     Search(SearchArgs),
+    /// Mirror tags and attributes in a .tags.toml file in every directory
+    Sidecar(SidecarCommand),
+    /// Undo the last operation that changed the database
+    Undo,
+    /// Add tags and attributes from the directories files are in, using a pattern like "photos/{year}/{place}/*"
+    TagFromPath(TagFromPathArgs),
+    /// Update database when files are moved or deleted
+    Watch,
+    /// Sync tags and attributes with extended attributes of files
+    #[cfg(unix)]
+    Xattr(XattrCommand),
 }
 
 #[derive(StructOpt)]
@@ -39,6 +76,39 @@ pub struct TaggingArgs {
     pub file_selection: FileSelection,
 }
 
+#[derive(StructOpt)]
+pub struct CheckArgs {
+    /// Fix problems found
+    #[structopt(long)]
+    pub repair: bool,
+}
+
+#[derive(StructOpt)]
+pub struct CleanupArgs {
+    /// Also compare content of missing files with untracked files to find moved files.
+    /// Hashes all tracked files that changed since the last run, which is slow for large files
+    #[structopt(long)]
+    pub relink: bool,
+}
+
+#[derive(StructOpt)]
+pub struct RemoveArgs {
+    /// Tags to remove from files. Use `<key>:*` to remove all values of a key
+    #[structopt(short, long)]
+    pub tags: Vec<String>,
+    /// Attribute keys to remove all values of
+    #[structopt(short, long = "key")]
+    pub keys: Vec<String>,
+    #[structopt(flatten)]
+    pub file_selection: FileSelection,
+}
+
+#[derive(StructOpt)]
+pub struct ClearArgs {
+    #[structopt(flatten)]
+    pub file_selection: FileSelection,
+}
+
 
 /// Cli options for selecting files
 #[derive(StructOpt)]
@@ -77,6 +147,72 @@ impl Into<FileSearchSettings> for &FileSelection {
     }
 }
 
+#[derive(StructOpt)]
+pub struct ForgetArgs {
+    #[structopt(flatten)]
+    pub file_selection: FileSelection,
+}
+
+#[derive(StructOpt)]
+pub struct ExtractArgs {
+    /// Parse attributes out of file names without extension instead, like "{artist} - {title}"
+    #[structopt(long)]
+    pub template: Option<seaserpent::template::Template>,
+    #[structopt(flatten)]
+    pub file_selection: FileSelection,
+}
+
+#[derive(StructOpt)]
+pub struct ExportArgs {
+    /// Output format: json, csv or tsv
+    #[structopt(long, default_value = "json")]
+    pub format: seaserpent::database::ExportFormat,
+    /// File to write to. Writes to stdout if not set
+    #[structopt(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(StructOpt)]
+pub struct ImportArgs {
+    /// Input format: json, csv, tsv or tmsu
+    #[structopt(long, default_value = "json")]
+    pub format: ImportFormat,
+    /// How to handle files already in the database: merge or replace their tags
+    #[structopt(long, default_value = "merge")]
+    pub mode: seaserpent::database::ImportMode,
+    /// File to import, or tmsu followed by the path of a TMSU database
+    pub file: PathBuf,
+    /// Path of the TMSU database, if the file to import is given as tmsu
+    pub tmsu_database: Option<PathBuf>,
+}
+
+/// Formats that can be imported
+#[derive(Clone, Copy)]
+pub enum ImportFormat {
+    /// File created by export
+    Export(seaserpent::database::ExportFormat),
+    /// TMSU database file
+    Tmsu,
+}
+
+impl FromStr for ImportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tmsu" => Ok(ImportFormat::Tmsu),
+            _ => s.parse().map(ImportFormat::Export),
+        }
+    }
+}
+
+#[derive(StructOpt)]
+pub struct HistoryArgs {
+    /// Number of operations to show
+    #[structopt(short = "n", long, default_value = "20")]
+    pub limit: i64,
+}
+
 #[derive(StructOpt)]
 pub struct InfoArgs {
     #[structopt(flatten)]
@@ -84,11 +220,152 @@ pub struct InfoArgs {
 }
 
 
+#[derive(StructOpt)]
+pub struct LogArgs {
+    /// File to show changes of
+    pub file: PathBuf,
+}
+
+
+#[derive(StructOpt)]
+pub struct CopyArgs {
+    /// Overwrite destination if it already exists
+    #[structopt(long)]
+    pub force: bool,
+    /// Don't copy tags and attributes with this key. Tags are matched by name
+    #[structopt(long)]
+    pub drop_key: Vec<String>,
+    /// Only copy tags and attributes with this key. Tags are matched by name, so unlisted tags are dropped
+    #[structopt(long)]
+    pub keep_key: Vec<String>,
+    /// File or directory to copy
+    pub source: PathBuf,
+    /// New path. If it is an existing directory the source is copied into it
+    pub destination: PathBuf,
+}
+
+impl Into<seaserpent::database::CopyOptions> for &CopyArgs {
+    fn into(self) -> seaserpent::database::CopyOptions {
+        seaserpent::database::CopyOptions {
+            overwrite: self.force,
+            drop_keys: self.drop_key.clone(),
+            keep_keys: self.keep_key.clone(),
+        }
+    }
+}
+
+#[derive(StructOpt)]
+pub struct MoveArgs {
+    /// Overwrite destination if it already exists
+    #[structopt(long)]
+    pub force: bool,
+    /// File or directory to move
+    pub source: PathBuf,
+    /// New path. If it is an existing directory the source is moved into it
+    pub destination: PathBuf,
+}
+
+
+#[derive(StructOpt)]
+pub struct RmArgs {
+    /// Move files to trash and keep their tags, so they can be restored
+    #[structopt(long)]
+    pub trash: bool,
+    #[structopt(flatten)]
+    pub file_selection: FileSelection,
+}
+
+#[derive(StructOpt)]
+pub struct RestoreArgs {
+    /// Original paths of files to restore
+    pub files: Vec<PathBuf>,
+}
+
+
 #[derive(StructOpt)]
 pub struct RenameArgs {
     /// Rename template
     #[structopt(long)]
     pub template: String,
+    /// Only print the old and new paths
+    #[structopt(long)]
+    pub dry_run: bool,
+    /// What to do with files whose new path is taken: skip, suffix or abort
+    #[structopt(long, default_value = "abort")]
+    pub on_conflict: seaserpent::database::ConflictStrategy,
+    #[structopt(flatten)]
+    pub file_selection: FileSelection,
+}
+
+
+#[derive(StructOpt)]
+pub struct OrganizeArgs {
+    /// Template for new paths like "{artist}/{album}/{track:02} {title}". The extension of the file is added to it
+    #[structopt(long)]
+    pub template: String,
+    /// Remove directories left empty by moving files
+    #[structopt(long)]
+    pub remove_empty_dirs: bool,
+    /// Only print where files would be moved
+    #[structopt(long)]
+    pub dry_run: bool,
+    /// What to do with files whose new path is taken: skip, suffix or abort
+    #[structopt(long, default_value = "abort")]
+    pub on_conflict: seaserpent::database::ConflictStrategy,
+    #[structopt(flatten)]
+    pub file_selection: FileSelection,
+}
+
+
+#[derive(StructOpt)]
+pub struct TagFromPathArgs {
+    /// Pattern for paths relative to the database root. Fields become attributes, {#} becomes a tag,
+    /// * matches any text and ** any number of directories
+    #[structopt(long)]
+    pub pattern: seaserpent::template::PathPattern,
+    #[structopt(flatten)]
+    pub file_selection: FileSelection,
+}
+
+
+#[derive(StructOpt)]
+pub enum SidecarCommand {
+    /// Write sidecar files from the database
+    Write(SidecarWriteArgs),
+    /// Add tags and attributes from sidecar files to the database
+    Read(SidecarReadArgs),
+}
+
+#[derive(StructOpt)]
+pub struct SidecarWriteArgs {
+    /// Directory to write sidecar files in, including all directories below it
+    #[structopt(default_value = ".")]
+    pub path: PathBuf,
+}
+
+#[derive(StructOpt)]
+pub struct SidecarReadArgs {
+    /// How to handle files already in the database: merge or replace their tags
+    #[structopt(long, default_value = "merge")]
+    pub mode: seaserpent::database::ImportMode,
+    /// Directory to read sidecar files from, including all directories below it
+    #[structopt(default_value = ".")]
+    pub path: PathBuf,
+}
+
+
+#[cfg(unix)]
+#[derive(StructOpt)]
+pub enum XattrCommand {
+    /// Write tags and attributes to extended attributes of files
+    Push(XattrArgs),
+    /// Add tags and attributes from extended attributes of files to the database
+    Pull(XattrArgs),
+}
+
+#[cfg(unix)]
+#[derive(StructOpt)]
+pub struct XattrArgs {
     #[structopt(flatten)]
     pub file_selection: FileSelection,
 }
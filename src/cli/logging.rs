@@ -1,7 +1,8 @@
 use log::{Level, LevelFilter};
 use colored::Colorize;
 
-use crate::database::SearchResult;
+use crate::database::{Change, CheckReport, FileChange, ImportReport, JournalEntry, RenamePlan, SearchResult};
+use chrono::TimeZone;
 
 use thiserror::Error;
 use displaydoc::Display;
@@ -72,3 +73,134 @@ pub fn print_result_descriptive(result: &SearchResult) {
     }
     println!("");
 }
+
+/// Print problems found when checking database
+pub fn print_check_report(report: &CheckReport, repaired: bool) {
+    for error in &report.integrity_errors {
+        println!("{} {error}", "Integrity error:".red());
+    }
+    if report.orphaned_tags > 0 {
+        println!("{} tags belong to files not in the database", report.orphaned_tags);
+    }
+    if report.orphaned_attributes > 0 {
+        println!("{} attributes belong to files not in the database", report.orphaned_attributes);
+    }
+    for (normalized, paths) in &report.duplicate_paths {
+        println!("Paths pointing to {}:", normalized.to_string_lossy().underline());
+        for path in paths {
+            println!("- {}", path.display());
+        }
+    }
+    for path in &report.invalid_paths {
+        println!("Path outside database: {}", path.display());
+    }
+    for (path, tag) in &report.disallowed_tags {
+        println!("Tag not allowed: {} on {}", tag.to_string(), path.display());
+    }
+    if report.is_ok() {
+        log::info!("No problems found");
+    } else if repaired {
+        if !report.integrity_errors.is_empty() {
+            log::error!("Integrity errors can not be repaired");
+        }
+        log::info!("Repaired database");
+    }
+}
+
+/// Print problems found while importing files
+pub fn print_import_report(report: &ImportReport) {
+    for path in &report.invalid_paths {
+        println!("Path outside database: {}", path.display());
+    }
+    for (path, tag) in &report.disallowed_tags {
+        println!("Tag not allowed: {} on {}", tag.to_string(), path.display());
+    }
+    for conflict in &report.attribute_conflicts {
+        println!(
+            "{} {} on {}: {} in database, {} imported",
+            "Conflict:".yellow(),
+            conflict.key,
+            conflict.path.display(),
+            conflict.existing.join(", "),
+            conflict.imported.join(", ")
+        );
+    }
+    log::info!("Imported {} files", report.imported);
+}
+
+/// Print old and new paths of files moved by rename or organize
+pub fn print_rename_plan(plan: &RenamePlan) {
+    for (original, new) in &plan.moves {
+        println!("{} -> {}", original.display(), new.display().to_string().green());
+    }
+    for (path, new) in &plan.conflicts {
+        println!("{} {} -> {} is already taken", "Conflict:".yellow(), path.display(), new.display());
+    }
+    for path in &plan.missing_attributes {
+        println!("{} {} is missing attributes used in template", "Skipped:".yellow(), path.display());
+    }
+}
+
+/// Warn about files left out of a rename
+pub fn warn_skipped_renames(plan: &RenamePlan) {
+    for (path, new) in &plan.conflicts {
+        log::warn!("{} -> {} is already taken", path.display(), new.display());
+    }
+    for path in &plan.missing_attributes {
+        log::warn!("Skipped {}, it is missing attributes used in template", path.display());
+    }
+}
+
+/// Print operations stored in the journal
+pub fn print_history(entries: &Vec<JournalEntry>) {
+    for entry in entries {
+        println!(
+            "{} {} {} {} {} changes{}",
+            entry.id.to_string().yellow(),
+            format_timestamp(entry.timestamp),
+            entry.user.as_deref().unwrap_or("unknown"),
+            entry.operation.bold(),
+            entry.changes,
+            format_undone(entry.undone)
+        );
+    }
+}
+
+/// Print changes made to a single file
+pub fn print_file_changes(changes: &Vec<FileChange>) {
+    for file_change in changes {
+        let description = match &file_change.change {
+            Change::AddFile { .. } => "added to database".to_string(),
+            Change::AddTag { tag, .. } => format!("+{tag}").green().to_string(),
+            Change::RemoveTag { tag, .. } => format!("-{tag}").red().to_string(),
+            Change::AddAttribute { key, value, .. } => format!("+{key}:{value}").green().to_string(),
+            Change::RemoveAttribute { key, value, .. } => format!("-{key}:{value}").red().to_string(),
+            Change::RemoveFile { .. } => "removed from database".to_string(),
+            Change::MoveFile { from, to } => format!("moved from {} to {}", from.display(), to.display()),
+        };
+        println!(
+            "{} {} {} {}{}",
+            format_timestamp(file_change.timestamp),
+            file_change.user.as_deref().unwrap_or("unknown"),
+            file_change.operation.bold(),
+            description,
+            format_undone(file_change.undone)
+        );
+    }
+}
+
+/// Format unix timestamp as local time
+fn format_timestamp(timestamp: i64) -> String {
+    chrono::Local.timestamp_opt(timestamp, 0)
+        .single()
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+fn format_undone(undone: bool) -> String {
+    if undone {
+        " (undone)".bright_black().to_string()
+    } else {
+        String::new()
+    }
+}
@@ -1,13 +1,13 @@
 mod args;
 mod logging;
+mod watch;
 
-use args::{Command, TaggingArgs, InfoArgs, RenameArgs, SearchArgs, FileSelection};
+use args::{Command, TaggingArgs, CheckArgs, CleanupArgs, RemoveArgs, ClearArgs, CopyArgs, ExportArgs, ExtractArgs, ForgetArgs, HistoryArgs, ImportArgs, ImportFormat, InfoArgs, LogArgs, MoveArgs, OrganizeArgs, RenameArgs, RestoreArgs, RmArgs, SearchArgs, SidecarCommand, TagFromPathArgs, FileSelection};
+#[cfg(unix)]
+use args::XattrCommand;
 use structopt::StructOpt;
-use std::{
-    str::FromStr,
-    path::PathBuf,
-};
-use seaserpent::{database, format, search, utils};
+use std::path::PathBuf;
+use seaserpent::{database, search, utils};
 
 use thiserror::Error;
 use displaydoc::Display;
@@ -17,12 +17,16 @@ use displaydoc::Display;
 pub enum SeaSerpentError {
     /// {0}
     Database(#[from] database::DatabaseError),
-    /// Failed to format string
-    Formatting,
     /// {0}
     Search(#[from] search::SearchError),
     /// {0}
     Logging(#[from] logging::SeaSerpentLoggingError),
+    /// {0}
+    Watch(#[from] notify::Error),
+    /// {0}
+    Io(#[from] std::io::Error),
+    /// {0}
+    InvalidArguments(String),
 }
 
 fn main() -> Result<(), SeaSerpentError> {
@@ -30,12 +34,31 @@ fn main() -> Result<(), SeaSerpentError> {
     logging::setup_logger(args.log_level)?;
     let result = match args.command {
         Command::Add(add_args) => add_tags(&add_args),
-        Command::Cleanup => cleanup(),
+        Command::Check(check_args) => check(&check_args),
+        Command::Cleanup(cleanup_args) => cleanup(&cleanup_args),
+        Command::Clear(clear_args) => clear_tags(&clear_args),
+        Command::Cp(copy_args) => copy_file(&copy_args),
+        Command::Export(export_args) => export(&export_args),
+        Command::Extract(extract_args) => extract(&extract_args),
+        Command::Forget(forget_args) => forget(&forget_args),
+        Command::History(history_args) => history(&history_args),
+        Command::Import(import_args) => import(&import_args),
         Command::Info(info_args) => print_info(&info_args),
         Command::Init => initialize_database(),
+        Command::Log(log_args) => print_log(&log_args),
+        Command::Mv(move_args) => move_file(&move_args),
+        Command::Organize(organize_args) => organize(&organize_args),
         Command::Remove(remove_args) => remove_tags(&remove_args),
         Command::Rename(rename_args) => rename(&rename_args),
+        Command::Restore(restore_args) => restore(&restore_args),
+        Command::Rm(rm_args) => remove_files(&rm_args),
         Command::Search(search_args) => search(&search_args),
+        Command::Sidecar(sidecar_command) => sync_sidecars(&sidecar_command),
+        Command::TagFromPath(tag_from_path_args) => tag_from_path(&tag_from_path_args),
+        Command::Undo => undo(),
+        Command::Watch => watch::watch(),
+        #[cfg(unix)]
+        Command::Xattr(xattr_command) => sync_xattrs(&xattr_command),
     };
     match result {
         Ok(_) => (),
@@ -51,37 +74,161 @@ fn get_files(file_selection: &FileSelection) -> Vec<PathBuf> {
 /// Add new tags to file
 fn add_tags(args: &TaggingArgs) -> Result<(), SeaSerpentError> {
     let mut database = database::Database::load_from_current_dir()?;
-    for file in get_files(&args.file_selection) {
-        for tag in &args.tags {
-            database.add_tag(&file, tag)?;
+    let _lock = database.lock()?;
+    let files = get_files(&args.file_selection);
+    database.journaled("add", |database| database.transaction(|database| {
+        for file in &files {
+            for tag in &args.tags {
+                database.add_tag(file, tag)?;
+            }
         }
-    }
-    Ok(())
+        Ok(())
+    }))
 }
 
 /// Remove tags from files
-fn remove_tags(args: &TaggingArgs) -> Result<(), SeaSerpentError> {
+fn remove_tags(args: &RemoveArgs) -> Result<(), SeaSerpentError> {
     let mut database = database::Database::load_from_current_dir()?;
-    for file in get_files(&args.file_selection) {
-        for tag in &args.tags {
-            match database.remove_tag(&file, tag) {
+    let _lock = database.lock()?;
+    let files = get_files(&args.file_selection);
+    database.journaled("remove", |database| database.transaction(|database| {
+        for file in &files {
+            for tag in &args.tags {
+                match database.remove_tag(file, tag) {
+                    // Ignore file not found
+                    Ok(_) | Err(database::DatabaseError::FileNotFound(_)) => (),
+                    Err(err) => return Err(SeaSerpentError::Database(err)),
+                }
+            }
+            for key in &args.keys {
+                match database.remove_attribute(file, key) {
+                    // Ignore file not found
+                    Ok(_) | Err(database::DatabaseError::FileNotFound(_)) => (),
+                    Err(err) => return Err(SeaSerpentError::Database(err)),
+                }
+            }
+        }
+        Ok(())
+    }))
+}
+
+/// Remove all tags and attributes from files
+fn clear_tags(args: &ClearArgs) -> Result<(), SeaSerpentError> {
+    let mut database = database::Database::load_from_current_dir()?;
+    let _lock = database.lock()?;
+    let files = get_files(&args.file_selection);
+    database.journaled("clear", |database| database.transaction(|database| {
+        for file in &files {
+            match database.clear_file(file) {
                 // Ignore file not found
                 Ok(_) | Err(database::DatabaseError::FileNotFound(_)) => (),
                 Err(err) => return Err(SeaSerpentError::Database(err)),
             }
         }
-    }
-    Ok(())
+        Ok(())
+    }))
 }
 
 /// Remove files from database that does not exist
-fn cleanup() -> Result<(), SeaSerpentError> {
+fn cleanup(args: &CleanupArgs) -> Result<(), SeaSerpentError> {
     let mut database = database::Database::load_from_current_dir()?;
-    match database.cleanup() {
-        // Ignore file not found
-        Ok(_) | Err(database::DatabaseError::FileNotFound(_)) => (),
-        Err(err) => return Err(SeaSerpentError::Database(err)),
-    }
+    let _lock = database.lock()?;
+    database.journaled("cleanup", |database| {
+        for (original, new) in database.relink(args.relink)? {
+            log::info!("Relinked {} to {}", original.display(), new.display());
+        }
+        match database.cleanup() {
+            // Ignore file not found
+            Ok(_) | Err(database::DatabaseError::FileNotFound(_)) => (),
+            Err(err) => return Err(SeaSerpentError::Database(err)),
+        }
+        Ok(())
+    })
+}
+
+/// Remove files from database without touching them on disk
+fn forget(args: &ForgetArgs) -> Result<(), SeaSerpentError> {
+    let mut database = database::Database::load_from_current_dir()?;
+    let _lock = database.lock()?;
+    // Directories are forgotten together with everything below them, even files no longer on disk
+    let files = if args.file_selection.recursive {
+        let settings = utils::files::FileSearchSettings {
+            recursive: false,
+            stdin: args.file_selection.stdin,
+            filetype_filter: utils::files::FiletypeFilter::All,
+        };
+        utils::files::get_files(&args.file_selection.files, settings)
+    } else {
+        get_files(&args.file_selection)
+    };
+    database.journaled("forget", |database| database.transaction(|database| {
+        for file in &files {
+            match database.forget_file(file) {
+                Ok(_) => log::info!("Removed {} from database", file.display()),
+                // Ignore file not found
+                Err(database::DatabaseError::FileNotFound(_)) => (),
+                Err(err) => return Err(SeaSerpentError::Database(err)),
+            }
+        }
+        Ok(())
+    }))
+}
+
+/// Add metadata embedded in files as attributes
+fn extract(args: &ExtractArgs) -> Result<(), SeaSerpentError> {
+    let mut database = database::Database::load_from_current_dir()?;
+    let _lock = database.lock()?;
+    let files = get_files(&args.file_selection);
+    let extractors = database.extractors();
+    database.journaled("extract", |database| database.transaction(|database| {
+        for file in &files {
+            let result = match &args.template {
+                Some(template) => match database.extract_from_name(file, template) {
+                    Ok(None) => {
+                        log::warn!("{} doesn't match template", file.display());
+                        continue;
+                    },
+                    result => result.map(Option::unwrap_or_default),
+                },
+                None => database.extract_with(file, &extractors),
+            };
+            match result {
+                Ok(attributes) => for attribute in attributes {
+                    log::info!("{}: {}", file.display(), attribute.to_string());
+                },
+                // Keep going if a single file can't be read
+                Err(database::DatabaseError::Extract(err)) => log::error!("{}", err),
+                Err(err) => return Err(SeaSerpentError::Database(err)),
+            }
+        }
+        Ok(())
+    }))
+}
+
+/// Add tags and attributes from the paths of files
+fn tag_from_path(args: &TagFromPathArgs) -> Result<(), SeaSerpentError> {
+    let mut database = database::Database::load_from_current_dir()?;
+    let _lock = database.lock()?;
+    let files = get_files(&args.file_selection);
+    database.journaled("tag-from-path", |database| database.transaction(|database| {
+        for file in &files {
+            match database.tag_from_path(file, &args.pattern)? {
+                Some(tags) => for tag in tags {
+                    log::info!("{}: {}", file.display(), tag.to_string());
+                },
+                None => log::debug!("{} doesn't match pattern", file.display()),
+            }
+        }
+        Ok(())
+    }))
+}
+
+/// Check database for inconsistencies
+fn check(args: &CheckArgs) -> Result<(), SeaSerpentError> {
+    let mut database = database::Database::load_from_current_dir()?;
+    let _lock = if args.repair { Some(database.lock()?) } else { None };
+    let report = database.journaled("check", |database| database.check(args.repair))?;
+    logging::print_check_report(&report, args.repair);
     Ok(())
 }
 
@@ -106,17 +253,220 @@ fn initialize_database() -> Result<(), SeaSerpentError> {
 /// Rename file based on template and attributes
 fn rename(rename_args: &RenameArgs) -> Result<(), SeaSerpentError> {
     let mut database = database::Database::load_from_current_dir()?;
-    for file in get_files(&rename_args.file_selection) {
-        let fileinfo = database.get_file_info(&file)?;
-        let new_path_str = format::format_result(&fileinfo, &rename_args.template)
-            .map_err(|_| SeaSerpentError::Formatting)?;
-        let new_path = std::path::PathBuf::from_str(&new_path_str).unwrap();
-        database.move_file(&file, &new_path)?;
-        log::info!("Moved {} to {}", file.display(), new_path.display());
+    let _lock = database.lock()?;
+    let files = get_files(&rename_args.file_selection);
+    let plan = database.plan_rename(&files, &rename_args.template, rename_args.on_conflict)?;
+    if rename_args.dry_run {
+        logging::print_rename_plan(&plan);
+        return Ok(());
+    }
+    logging::warn_skipped_renames(&plan);
+    // Files moved before an error stay moved, since moves on disk can't be rolled back
+    database.journaled("rename", |database| database.rename_files(&plan))?;
+    Ok(())
+}
+
+/// Move files into directories created from template and attributes
+fn organize(args: &OrganizeArgs) -> Result<(), SeaSerpentError> {
+    let mut database = database::Database::load_from_current_dir()?;
+    let _lock = database.lock()?;
+    let files = get_files(&args.file_selection);
+    let plan = database.plan_organize(&files, &args.template, args.on_conflict)?;
+    logging::print_rename_plan(&plan);
+    log::info!("{} files to move", plan.moves.len());
+    if args.dry_run {
+        return Ok(());
+    }
+    database.journaled("organize", |database| database.organize(&plan, args.remove_empty_dirs))?;
+    Ok(())
+}
+
+/// Copy file or directory on disk and in database
+fn copy_file(args: &CopyArgs) -> Result<(), SeaSerpentError> {
+    let mut database = database::Database::load_from_current_dir()?;
+    let _lock = database.lock()?;
+    let destination = match args.source.file_name() {
+        Some(file_name) if args.destination.is_dir() => args.destination.join(file_name),
+        _ => args.destination.clone(),
+    };
+    database.journaled("cp", |database| database.copy_file(&args.source, &destination, &args.into()))?;
+    log::info!("Copied {} to {}", args.source.display(), destination.display());
+    Ok(())
+}
+
+/// Move file or directory on disk and in database
+fn move_file(args: &MoveArgs) -> Result<(), SeaSerpentError> {
+    let mut database = database::Database::load_from_current_dir()?;
+    let _lock = database.lock()?;
+    let destination = match args.source.file_name() {
+        Some(file_name) if args.destination.is_dir() => args.destination.join(file_name),
+        _ => args.destination.clone(),
+    };
+    database.journaled("mv", |database| database.move_file(&args.source, &destination, args.force))?;
+    log::info!("Moved {} to {}", args.source.display(), destination.display());
+    Ok(())
+}
+
+/// Delete files from disk and database
+fn remove_files(args: &RmArgs) -> Result<(), SeaSerpentError> {
+    let mut database = database::Database::load_from_current_dir()?;
+    let _lock = database.lock()?;
+    database.journaled("rm", |database| {
+        for file in get_files(&args.file_selection) {
+            // Files can already be removed together with their directory
+            if !file.exists() {
+                continue;
+            }
+            if args.trash {
+                database.trash_file(&file)?;
+                log::info!("Moved {} to trash", file.display());
+            } else {
+                database.delete_file(&file)?;
+                log::info!("Removed {}", file.display());
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Restore files from trash
+fn restore(args: &RestoreArgs) -> Result<(), SeaSerpentError> {
+    let mut database = database::Database::load_from_current_dir()?;
+    let _lock = database.lock()?;
+    if args.files.is_empty() {
+        for file in database.trashed_files()? {
+            println!("{}", file.display());
+        }
+        return Ok(());
+    }
+    database.journaled("restore", |database| {
+        for file in &args.files {
+            database.restore_file(file)?;
+            log::info!("Restored {}", file.display());
+        }
+        Ok(())
+    })
+}
+
+/// Print operations stored in the journal
+fn history(args: &HistoryArgs) -> Result<(), SeaSerpentError> {
+    let mut database = database::Database::load_from_current_dir()?;
+    let entries = database.history(args.limit)?;
+    logging::print_history(&entries);
+    Ok(())
+}
+
+/// Print changes made to a file
+fn print_log(args: &LogArgs) -> Result<(), SeaSerpentError> {
+    let mut database = database::Database::load_from_current_dir()?;
+    let changes = database.file_changes(&args.file)?;
+    logging::print_file_changes(&changes);
+    Ok(())
+}
+
+/// Undo the last operation stored in the journal
+fn undo() -> Result<(), SeaSerpentError> {
+    let mut database = database::Database::load_from_current_dir()?;
+    let _lock = database.lock()?;
+    match database.undo()? {
+        Some(entry) => log::info!("Undid {} ({} changes)", entry.operation, entry.changes),
+        None => log::info!("Nothing to undo"),
+    }
+    Ok(())
+}
+
+/// Export all files in database
+fn export(args: &ExportArgs) -> Result<(), SeaSerpentError> {
+    let mut database = database::Database::load_from_current_dir()?;
+    let content = database.export()?.serialize(args.format)?;
+    match &args.output {
+        Some(output) => std::fs::write(output, content)?,
+        None => print!("{content}"),
+    }
+    Ok(())
+}
+
+/// Import files from file created by export or from TMSU database
+fn import(args: &ImportArgs) -> Result<(), SeaSerpentError> {
+    let mut database = database::Database::load_from_current_dir()?;
+    let _lock = database.lock()?;
+    // `import tmsu <path>` is the same as `import --format tmsu <path>`
+    let (format, file) = match &args.tmsu_database {
+        Some(tmsu_database) if args.file.as_os_str() == "tmsu" => (ImportFormat::Tmsu, tmsu_database),
+        Some(_) => return Err(SeaSerpentError::InvalidArguments("Expected import tmsu <path>".to_string())),
+        None => (args.format, &args.file),
+    };
+    let export = match format {
+        ImportFormat::Export(format) => {
+            let content = std::fs::read_to_string(file)?;
+            database::Export::parse(&content, format)?
+        },
+        ImportFormat::Tmsu => {
+            let tmsu = database.read_tmsu(file)?;
+            for path in &tmsu.outside_root {
+                println!("Path outside database: {}", path.display());
+            }
+            tmsu.export
+        },
+    };
+    let report = database.journaled("import", |database| database.import(&export, args.mode))?;
+    logging::print_import_report(&report);
+    Ok(())
+}
+
+/// Write tags to or read tags from sidecar files
+fn sync_sidecars(command: &SidecarCommand) -> Result<(), SeaSerpentError> {
+    let mut database = database::Database::load_from_current_dir()?;
+    match command {
+        SidecarCommand::Write(args) => {
+            for sidecar in database.write_sidecars(&args.path)? {
+                log::debug!("Wrote {}", sidecar.display());
+            }
+        },
+        SidecarCommand::Read(args) => {
+            let _lock = database.lock()?;
+            let report = database.journaled("sidecar read", |database| database.read_sidecars(&args.path, args.mode))?;
+            logging::print_import_report(&report);
+        },
     }
     Ok(())
 }
 
+/// Write tags to or read tags from extended attributes of files
+#[cfg(unix)]
+fn sync_xattrs(command: &XattrCommand) -> Result<(), SeaSerpentError> {
+    let mut database = database::Database::load_from_current_dir()?;
+    match command {
+        XattrCommand::Push(args) => {
+            for file in get_files(&args.file_selection) {
+                match database.push_xattrs(&file) {
+                    // Ignore files not in database
+                    Ok(_) | Err(database::DatabaseError::FileNotFound(_)) => (),
+                    // Filesystem may not support extended attributes
+                    Err(err @ database::DatabaseError::Xattr(_)) => log::warn!("{}", err),
+                    Err(err) => return Err(SeaSerpentError::Database(err)),
+                }
+            }
+            Ok(())
+        },
+        XattrCommand::Pull(args) => {
+            let _lock = database.lock()?;
+            let files = get_files(&args.file_selection);
+            database.journaled("xattr pull", |database| database.transaction(|database| {
+                for file in &files {
+                    match database.pull_xattrs(file) {
+                        Ok(_) => (),
+                        // Filesystem may not support extended attributes
+                        Err(err @ database::DatabaseError::Xattr(_)) => log::warn!("{}", err),
+                        Err(err) => return Err(SeaSerpentError::Database(err)),
+                    }
+                }
+                Ok(())
+            }))
+        },
+    }
+}
+
 /// Search for files in database
 fn search(args: &SearchArgs) -> Result<(), SeaSerpentError> {
     let mut database = database::Database::load_from_current_dir()?;
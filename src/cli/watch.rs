@@ -0,0 +1,95 @@
+use crate::SeaSerpentError;
+use seaserpent::database::{Database, DatabaseError, DeletionPolicy};
+use notify::{
+    Watcher, RecursiveMode, EventKind,
+    event::{ModifyKind, RenameMode},
+};
+use std::path::Path;
+
+/// Watch the database root and update the database when files are moved or deleted
+pub fn watch() -> Result<(), SeaSerpentError> {
+    let mut database = Database::load_from_current_dir()?;
+    let root_dir = database.root_dir()?.to_path_buf();
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(&root_dir, RecursiveMode::Recursive)?;
+    log::info!("Watching {}", root_dir.display());
+    for event in receiver {
+        let event = match event {
+            Ok(event) => event,
+            Err(err) => {
+                log::error!("{}", err);
+                continue;
+            }
+        };
+        // Ignore changes made to the database itself
+        if event.paths.iter().any(|path| database.is_internal_path(path)) {
+            continue;
+        }
+        let result = match (event.kind, event.paths.as_slice()) {
+            (EventKind::Modify(ModifyKind::Name(RenameMode::Both)), [original, new]) =>
+                handle_move(&mut database, original, new),
+            (EventKind::Remove(_), paths) => paths.iter()
+                .try_for_each(|path| handle_removal(&mut database, path)),
+            _ => Ok(()),
+        };
+        // Keep watching if a single change fails
+        if let Err(err) = result {
+            log::error!("{}", err);
+        }
+    }
+    Ok(())
+}
+
+fn handle_move(database: &mut Database, original: &Path, new: &Path) -> Result<(), DatabaseError> {
+    if !is_tracked(database, original) {
+        return Ok(());
+    }
+    database.journaled("watch", |database| database.track_move(original, new))?;
+    log::info!("Moved {} to {}", original.display(), new.display());
+    Ok(())
+}
+
+fn handle_removal(database: &mut Database, path: &Path) -> Result<(), DatabaseError> {
+    if !is_tracked(database, path) {
+        return Ok(());
+    }
+    match database.deletion_policy() {
+        DeletionPolicy::Remove => {
+            database.journaled("watch", |database| database.forget_file(path))?;
+            log::info!("Removed {} from database", path.display());
+        },
+        DeletionPolicy::Queue => {
+            log::info!("{} was deleted and will be removed on next cleanup", path.display());
+        },
+    }
+    Ok(())
+}
+
+/// Returns true if `path` or any file below it is in the database.
+/// Directories often have no row of their own, only the files below them do.
+fn is_tracked(database: &mut Database, path: &Path) -> bool {
+    database.contains_files_below(path).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod test {
+    use seaserpent::database::Database;
+
+    #[test]
+    fn move_untagged_directory() {
+        let dir = std::env::temp_dir().join(format!("sea-serpent-watch-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("dir")).unwrap();
+        let dir = dir.canonicalize().unwrap();
+        let mut database = Database::init(&dir).unwrap();
+        std::fs::write(dir.join("dir/file"), "").unwrap();
+        database.add_tag(&dir.join("dir/file"), &"tag".to_string()).unwrap();
+        std::fs::rename(dir.join("dir"), dir.join("moved")).unwrap();
+        super::handle_move(&mut database, &dir.join("dir"), &dir.join("moved")).unwrap();
+        assert!(database.get_file_info(&dir.join("dir/file")).is_err());
+        assert!(database.get_file_info(&dir.join("moved/file")).unwrap().tags.contains("tag"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+}
@@ -0,0 +1,172 @@
+use super::{Database, DatabaseError, Tag, find};
+use std::{
+    path::PathBuf,
+    collections::BTreeMap,
+};
+use colored::Colorize;
+
+/// Problems found when checking the integrity of a database
+#[derive(Debug, Default)]
+pub struct CheckReport {
+    /// Number of tags belonging to files that are not in the database
+    pub orphaned_tags: i64,
+    /// Number of attributes belonging to files that are not in the database
+    pub orphaned_attributes: i64,
+    /// Paths that point to the same file or are not normalized, grouped by their normalized path
+    pub duplicate_paths: Vec<(PathBuf, Vec<PathBuf>)>,
+    /// Paths that are absolute or point outside the database root
+    pub invalid_paths: Vec<PathBuf>,
+    /// Tags and attributes rejected by the whitelist or blacklist
+    pub disallowed_tags: Vec<(PathBuf, Tag)>,
+    /// Errors reported by sqlite `integrity_check`
+    pub integrity_errors: Vec<String>,
+}
+
+impl CheckReport {
+
+    /// Returns true if no problems were found
+    pub fn is_ok(&self) -> bool {
+        self.orphaned_tags == 0
+            && self.orphaned_attributes == 0
+            && self.duplicate_paths.is_empty()
+            && self.invalid_paths.is_empty()
+            && self.disallowed_tags.is_empty()
+            && self.integrity_errors.is_empty()
+    }
+
+}
+
+impl Database {
+
+    /// Check the database for inconsistencies.
+    /// If `repair` is true all problems except sqlite integrity errors are fixed.
+    pub fn check(&mut self, repair: bool) -> Result<CheckReport, DatabaseError> {
+        let (orphaned_tags, orphaned_attributes) = self.storage.count_orphans()?;
+        let mut report = CheckReport {
+            orphaned_tags,
+            orphaned_attributes,
+            integrity_errors: self.storage.integrity_check()?,
+            ..Default::default()
+        };
+        let files = self.storage.get_all_files()?;
+        // Group paths by their normalized form to find duplicates
+        let mut normalized_paths: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+        for file in &files {
+            if find::is_contained_relative_path(&file.path) {
+                normalized_paths.entry(find::normalize_path(&file.path))
+                    .or_default()
+                    .push(file.path.clone());
+            } else {
+                report.invalid_paths.push(file.path.clone());
+            }
+        }
+        report.duplicate_paths = normalized_paths.into_iter()
+            .filter(|(normalized, paths)| paths.len() > 1 || paths[0] != *normalized)
+            .collect();
+        // Tags and attributes not allowed by config
+        for file in files {
+            let tags = file.tags.iter()
+                .map(|tag| Tag::Key(tag.clone()));
+            let attributes = file.attributes.iter()
+                .map(|(key, value)| Tag::KeyValue { key: key.clone(), value: value.clone() });
+            for tag in tags.chain(attributes) {
+                if !self.config.tag_allowed(&tag) {
+                    report.disallowed_tags.push((file.path.clone(), tag));
+                }
+            }
+        }
+        if repair {
+            self.transaction(|database| database.repair(&report))?;
+        }
+        Ok(report)
+    }
+
+    /// Fix problems found by `check`
+    fn repair(&mut self, report: &CheckReport) -> Result<(), DatabaseError> {
+        self.storage.remove_orphans()?;
+        for (path, tag) in &report.disallowed_tags {
+            log::debug!("Removing {} from {}", tag.to_string(), path.to_string_lossy().blue());
+            self.storage.remove_tag(path, tag)?;
+        }
+        for (normalized, paths) in &report.duplicate_paths {
+            for path in paths.iter().filter(|path| *path != normalized) {
+                log::debug!("Merging {} into {}", path.to_string_lossy().blue(), normalized.to_string_lossy().blue());
+                self.storage.merge_file(path, normalized)?;
+            }
+        }
+        let root_dir = self.root_dir()?.to_path_buf();
+        for path in &report.invalid_paths {
+            // Absolute paths inside the database root can be made relative
+            let relative_path = path.strip_prefix(&root_dir)
+                .ok()
+                .map(find::normalize_path)
+                .filter(|relative| find::is_contained_relative_path(relative));
+            match relative_path {
+                Some(relative_path) => {
+                    log::debug!("Merging {} into {}", path.to_string_lossy().blue(), relative_path.to_string_lossy().blue());
+                    self.storage.merge_file(path, &relative_path)?;
+                },
+                None => {
+                    log::debug!("Removing {} from database", path.to_string_lossy().blue());
+                    self.storage.remove_file(path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::Database;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn check_and_repair() {
+        let (dir, database) = super::super::create_test_database("check");
+        std::fs::write(dir.join(super::find::DATABASE_DIR).join("config.toml"), "blacklist = [\"bad\"]").unwrap();
+        let mut database = Database::load(database.path.clone()).unwrap();
+        let absolute = dir.join("absolute");
+        database.storage.add_tag(Path::new("./x"), &"a".to_string()).unwrap();
+        database.storage.add_tag(Path::new("x"), &"b".to_string()).unwrap();
+        database.storage.add_tag(Path::new("x"), &"bad".to_string()).unwrap();
+        database.storage.add_tag(&absolute, &"c".to_string()).unwrap();
+        database.storage.add_tag(Path::new("../outside"), &"d".to_string()).unwrap();
+
+        let report = database.check(false).unwrap();
+        assert!(!report.is_ok());
+        let mut duplicates = report.duplicate_paths.clone();
+        duplicates.iter_mut().for_each(|(_, paths)| paths.sort());
+        assert_eq!(duplicates, vec![(PathBuf::from("x"), vec![PathBuf::from("./x"), PathBuf::from("x")])]);
+        let mut invalid_paths = report.invalid_paths.clone();
+        invalid_paths.sort();
+        let mut expected = vec![PathBuf::from("../outside"), absolute.clone()];
+        expected.sort();
+        assert_eq!(invalid_paths, expected);
+        let disallowed_tags = report.disallowed_tags.iter()
+            .map(|(path, tag)| (path.clone(), tag.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(disallowed_tags, vec![(PathBuf::from("x"), "bad".to_string())]);
+        // Nothing is changed without repair
+        assert_eq!(database.storage.get_all_files().unwrap().len(), 4);
+
+        database.check(true).unwrap();
+        let mut files = database.storage.get_all_files().unwrap()
+            .into_iter()
+            .map(|file| {
+                let mut tags = file.tags.into_iter().collect::<Vec<_>>();
+                tags.sort();
+                (file.path, tags)
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files, vec![
+            (PathBuf::from("absolute"), vec!["c".to_string()]),
+            (PathBuf::from("x"), vec!["a".to_string(), "b".to_string()]),
+        ]);
+        assert!(database.check(false).unwrap().is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+}
@@ -2,7 +2,41 @@ use std::path::{Path, PathBuf};
 use std::collections::HashMap;
 use serde::Deserialize;
 
-const CONFIG_FILE: &'static str = "config.toml";
+const CONFIG_FILE: &str = "config.toml";
+
+/// Milliseconds to wait for a locked database if nothing else is configured
+const DEFAULT_BUSY_TIMEOUT: u64 = 5000;
+
+/// Largest file in bytes hashed when it is tagged if nothing else is configured
+const DEFAULT_HASH_SIZE_LIMIT: u64 = 64 * 1024 * 1024;
+
+/// Attribute keys used by `extract` for metadata keys if nothing else is configured
+const DEFAULT_EXTRACT_KEYS: &[(&str, &str)] = &[
+    // ID3
+    ("TPE1", "artist"),
+    ("TALB", "album"),
+    ("TIT2", "title"),
+    ("TRCK", "track"),
+    ("TCON", "genre"),
+    ("TDRC", "year"),
+    ("TYER", "year"),
+    // Vorbis comments
+    ("ARTIST", "artist"),
+    ("ALBUM", "album"),
+    ("TITLE", "title"),
+    ("TRACKNUMBER", "track"),
+    ("GENRE", "genre"),
+    ("DATE", "year"),
+    // Exif
+    ("DateTimeOriginal", "date"),
+    ("Make", "camera_make"),
+    ("Model", "camera_model"),
+    // Pdf document info
+    ("Title", "title"),
+    ("Author", "author"),
+    ("Subject", "subject"),
+    ("Keywords", "keywords"),
+];
 
 /// Configuration for database
 #[derive(Default, Debug, Deserialize)]
@@ -14,6 +48,43 @@ pub struct DatabaseConfig {
     /// Mappings from alias to tag list
     #[serde(default)]
     aliases: HashMap<String, Vec<String>>,
+    /// Milliseconds to wait for other processes using the database
+    busy_timeout: Option<u64>,
+    /// Largest file in bytes hashed when it is tagged
+    hash_size_limit: Option<u64>,
+    /// What to do when a file is deleted while watching the database
+    #[serde(default)]
+    watch_deletions: DeletionPolicy,
+    /// Mappings from metadata key to attribute key used by `extract`.
+    /// An empty attribute key disables a default mapping.
+    #[serde(default)]
+    extract_keys: HashMap<String, String>,
+    /// External commands used by `extract`
+    #[serde(default)]
+    extractors: Vec<ExtractorConfig>,
+}
+
+/// External command used by `extract` for files with some extensions or MIME types
+#[derive(Debug, Deserialize)]
+pub struct ExtractorConfig {
+    /// Program and arguments. The path of the file is added as last argument.
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    #[serde(default)]
+    pub mime_types: Vec<String>,
+}
+
+/// What to do when a file is deleted while watching the database
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeletionPolicy {
+    /// Remove the file from the database immediately
+    Remove,
+    /// Keep the file in the database until the next cleanup.
+    /// This allows cleanup to relink the tags if the file shows up somewhere else.
+    #[default]
+    Queue,
 }
 
 impl DatabaseConfig {
@@ -34,6 +105,36 @@ impl DatabaseConfig {
         matches_whitelist && matches_blacklist
     }
 
+    /// Returns the number of milliseconds to wait for other processes using the database
+    pub fn busy_timeout(&self) -> u64 {
+        self.busy_timeout.unwrap_or(DEFAULT_BUSY_TIMEOUT)
+    }
+
+    /// Returns the size in bytes of the largest file hashed when it is tagged
+    pub fn hash_size_limit(&self) -> u64 {
+        self.hash_size_limit.unwrap_or(DEFAULT_HASH_SIZE_LIMIT)
+    }
+
+    /// Returns what to do when a file is deleted while watching the database
+    pub fn watch_deletions(&self) -> DeletionPolicy {
+        self.watch_deletions
+    }
+
+    /// Returns the attribute keys that metadata keys are stored as by `extract`.
+    /// Configured keys replace the default ones and metadata with an empty key is left out.
+    pub fn extract_keys(&self) -> HashMap<String, String> {
+        let mut keys: HashMap<String, String> = DEFAULT_EXTRACT_KEYS.iter()
+            .map(|(metadata_key, attribute_key)| (metadata_key.to_string(), attribute_key.to_string()))
+            .collect();
+        keys.extend(self.extract_keys.clone());
+        keys.retain(|_, attribute_key| !attribute_key.is_empty());
+        keys
+    }
+
+    /// Returns the external commands used by `extract`
+    pub fn extractors(&self) -> &[ExtractorConfig] {
+        &self.extractors
+    }
 
 }
 
@@ -86,4 +187,38 @@ mod test {
         assert!(!config.tag_allowed(&Tag::new("tag_b")));
     }
 
+    #[test]
+    fn busy_timeout() {
+        assert_eq!(super::DatabaseConfig::default().busy_timeout(), super::DEFAULT_BUSY_TIMEOUT);
+        let config: super::DatabaseConfig = toml::from_str("busy_timeout = 100").unwrap();
+        assert_eq!(config.busy_timeout(), 100);
+    }
+
+    #[test]
+    fn hash_size_limit() {
+        assert_eq!(super::DatabaseConfig::default().hash_size_limit(), super::DEFAULT_HASH_SIZE_LIMIT);
+        let config: super::DatabaseConfig = toml::from_str("hash_size_limit = 0").unwrap();
+        assert_eq!(config.hash_size_limit(), 0);
+    }
+
+    #[test]
+    fn watch_deletions() {
+        assert_eq!(super::DatabaseConfig::default().watch_deletions(), super::DeletionPolicy::Queue);
+        let config: super::DatabaseConfig = toml::from_str("watch_deletions = \"remove\"").unwrap();
+        assert_eq!(config.watch_deletions(), super::DeletionPolicy::Remove);
+    }
+
+    #[test]
+    fn extract_keys() {
+        let config: super::DatabaseConfig = toml::from_str(
+            "[extract_keys]\nTPE1 = \"performer\"\nTALB = \"\"\nTXXX = \"comment\""
+        ).unwrap();
+        let keys = config.extract_keys();
+        assert_eq!(keys.get("TPE1").map(String::as_str), Some("performer"));
+        assert_eq!(keys.get("TALB"), None);
+        assert_eq!(keys.get("TXXX").map(String::as_str), Some("comment"));
+        assert_eq!(keys.get("TIT2").map(String::as_str), Some("title"));
+        assert_eq!(keys.get("unknown"), None);
+    }
+
 }
@@ -0,0 +1,88 @@
+use super::{Database, DatabaseError, find};
+use std::path::{Path, PathBuf};
+use colored::Colorize;
+
+/// Options for copying files
+#[derive(Debug, Default)]
+pub struct CopyOptions {
+    /// Overwrite destination if it already exists
+    pub overwrite: bool,
+    /// Keys of tags and attributes that are not copied. The key of a tag is its name.
+    pub drop_keys: Vec<String>,
+    /// If not empty, only tags and attributes with these keys are copied.
+    /// The key of a tag is its name, so tags not listed are dropped.
+    pub keep_keys: Vec<String>,
+}
+
+impl CopyOptions {
+
+    /// Returns true if tags and attributes with `key` should be copied
+    pub fn copies_key(&self, key: &str) -> bool {
+        let dropped = self.drop_keys.iter().any(|x| x == key);
+        let kept = self.keep_keys.is_empty() || self.keep_keys.iter().any(|x| x == key);
+        kept && !dropped
+    }
+
+}
+
+impl Database {
+
+    /// Copy `original` to `new` on disk and copy all its tags and attributes in the database.
+    /// Directories are copied recursively.
+    /// Returns the paths of the new files in the database relative to the database root.
+    pub fn copy_file(&mut self, original: &Path, new: &Path, options: &CopyOptions) -> Result<Vec<PathBuf>, DatabaseError> {
+        let root_dir = self.root_dir()?.to_path_buf();
+        let original_relative = find::path_relative_to_db_root(original, &root_dir)?;
+        let new_relative = find::path_relative_to_db_root(new, &root_dir)?;
+        // Copying a directory into itself would never end
+        if new_relative.starts_with(&original_relative) {
+            return Err(DatabaseError::WriteToDisk(new.to_path_buf()));
+        }
+        let destination_exists = new.exists() || self.storage.contains_file(&new_relative)?;
+        if destination_exists && !options.overwrite {
+            return Err(DatabaseError::DestinationExists(new.to_path_buf()));
+        }
+        crate::utils::files::copy_recursive(original, new)
+            .map_err(|_| DatabaseError::WriteToDisk(new.to_path_buf()))?;
+        let copied = self.transaction(|database| {
+            if destination_exists {
+                database.storage.remove_files_below(&new_relative)?;
+            }
+            database.storage.copy_file(
+                &original_relative,
+                &new_relative,
+                &|key: &str| options.copies_key(key)
+            )
+        })?;
+        // Read metadata after committing, so other processes aren't blocked while walking large trees
+        for file in &copied {
+            log::debug!("Copied tags to {}", file.to_string_lossy().blue());
+            self.update_file_metadata(&root_dir.join(file), file)?;
+        }
+        Ok(copied)
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+
+    #[test]
+    fn copies_key() {
+        let options = super::CopyOptions {
+            drop_keys: vec!["id".to_string()],
+            ..Default::default()
+        };
+        assert!(options.copies_key("artist"));
+        assert!(!options.copies_key("id"));
+        let options = super::CopyOptions {
+            keep_keys: vec!["artist".to_string()],
+            ..Default::default()
+        };
+        assert!(options.copies_key("artist"));
+        assert!(!options.copies_key("id"));
+        // Tags are matched by name
+        assert!(!options.copies_key("tag_a"));
+    }
+
+}
@@ -23,8 +23,28 @@ pub enum DatabaseError {
     DatabaseConnection(#[from] diesel::ConnectionError),
     /// {0}
     Diesel(#[from] diesel::result::Error),
+    /// {0}
+    Json(#[from] serde_json::Error),
+    /// {0}
+    Csv(#[from] csv::Error),
     /// Invalid database dir
     InvalidRootDir,
     /// Failed to setup database
     DBSetup,
+    /// Could not move {0} to or from trash
+    Trash(PathBuf),
+    /// {0} already exists
+    DestinationExists(PathBuf),
+    /// Database is used by another process holding the lock on {0}
+    Locked(PathBuf),
+    /// Export format version {0} is not supported
+    UnsupportedExportVersion(u32),
+    /// Can't access extended attributes of {0}
+    Xattr(PathBuf),
+    /// Sidecar file {0} is not formatted correctly
+    InvalidSidecar(PathBuf),
+    /// {0} files would be moved to paths that are taken. Nothing was moved
+    RenameConflicts(usize),
+    /// {0}
+    Extract(#[from] crate::extract::ExtractError),
 }
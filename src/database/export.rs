@@ -0,0 +1,426 @@
+use super::{Database, DatabaseError, SearchResult, Tag, find};
+use std::{
+    path::PathBuf,
+    str::FromStr,
+    collections::BTreeSet,
+};
+use colored::Colorize;
+
+/// Version of the format written by `Database::export`
+pub const EXPORT_VERSION: u32 = 1;
+
+/// Separates tags and multiple values of the same attribute in csv and tsv files.
+/// Line breaks and backslashes in tags and values are escaped, so they survive a round trip.
+const VALUE_SEPARATOR: char = '\n';
+
+/// Name of the column with paths in csv and tsv files
+const PATH_COLUMN: &str = "path";
+
+/// Name of the column with tags in csv and tsv files
+const TAGS_COLUMN: &str = "tags";
+
+/// Added to the names of attribute columns in csv and tsv files that would collide with
+/// the path or tags column. Keys can't contain it, so it is never part of an actual key.
+const ATTRIBUTE_MARKER: char = ':';
+
+/// All files in a database together with their tags and attributes
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Export {
+    /// Version of the export format
+    pub version: u32,
+    /// Files sorted by path. Paths are relative to the database root.
+    pub files: Vec<SearchResult>,
+}
+
+/// File format of exports
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Json,
+    /// One row per file with tags in one column and a column for every attribute key
+    Csv,
+    /// Same as csv but separated by tabs
+    Tsv,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ExportFormat::Json),
+            "csv" => Ok(ExportFormat::Csv),
+            "tsv" => Ok(ExportFormat::Tsv),
+            _ => Err(format!("Unknown format {s}, expected json, csv or tsv")),
+        }
+    }
+}
+
+/// How imported files are combined with files already in the database
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportMode {
+    /// Add imported tags and attributes to the existing ones
+    Merge,
+    /// Replace the tags and attributes of existing files with the imported ones
+    Replace,
+}
+
+impl FromStr for ImportMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "merge" => Ok(ImportMode::Merge),
+            "replace" => Ok(ImportMode::Replace),
+            _ => Err(format!("Unknown import mode {s}, expected merge or replace")),
+        }
+    }
+}
+
+/// Problems found while importing files
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// Number of files imported
+    pub imported: usize,
+    /// Paths that are absolute or point outside the database root. These are not imported.
+    pub invalid_paths: Vec<PathBuf>,
+    /// Tags and attributes rejected by the whitelist or blacklist. These are not imported.
+    pub disallowed_tags: Vec<(PathBuf, Tag)>,
+    /// Attributes that already had other values in the database
+    pub attribute_conflicts: Vec<AttributeConflict>,
+}
+
+/// Attribute with different values in the database and in the imported data
+#[derive(Debug, PartialEq)]
+pub struct AttributeConflict {
+    pub path: PathBuf,
+    pub key: String,
+    /// Values in the database before importing
+    pub existing: Vec<String>,
+    /// Imported values
+    pub imported: Vec<String>,
+}
+
+impl Export {
+
+    /// Write export as `format`
+    pub fn serialize(&self, format: ExportFormat) -> Result<String, DatabaseError> {
+        match format {
+            ExportFormat::Json => Ok(serde_json::to_string_pretty(self)? + "\n"),
+            ExportFormat::Csv => self.serialize_table(b','),
+            ExportFormat::Tsv => self.serialize_table(b'\t'),
+        }
+    }
+
+    /// Read export written as `format`
+    pub fn parse(content: &str, format: ExportFormat) -> Result<Self, DatabaseError> {
+        match format {
+            ExportFormat::Json => Ok(serde_json::from_str(content)?),
+            ExportFormat::Csv => Self::parse_table(content, b','),
+            ExportFormat::Tsv => Self::parse_table(content, b'\t'),
+        }
+    }
+
+    fn serialize_table(&self, delimiter: u8) -> Result<String, DatabaseError> {
+        let keys: BTreeSet<&String> = self.files.iter()
+            .flat_map(|file| file.attributes.iter().map(|(key, _)| key))
+            .collect();
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .from_writer(Vec::new());
+        let header = [PATH_COLUMN.to_string(), TAGS_COLUMN.to_string()].into_iter()
+            .chain(keys.iter().map(|key| attribute_column(key)));
+        writer.write_record(header)?;
+        for file in &self.files {
+            let mut record = vec![file.path.to_string_lossy().to_string(), join_cell(&file.tags)];
+            for key in &keys {
+                record.push(join_cell(&attribute_values(file, key)));
+            }
+            writer.write_record(&record)?;
+        }
+        let content = writer.into_inner()
+            .map_err(|err| csv::Error::from(err.into_error()))?;
+        Ok(String::from_utf8_lossy(&content).into_owned())
+    }
+
+    /// Cells are parsed with the same rules as tags given on the command line,
+    /// so a tag cell like `key:value` becomes an attribute
+    fn parse_table(content: &str, delimiter: u8) -> Result<Self, DatabaseError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_reader(content.as_bytes());
+        let header = reader.headers()?.clone();
+        let mut files = Vec::new();
+        for record in reader.records() {
+            let record = record?;
+            let mut file = SearchResult {
+                path: PathBuf::new(),
+                tags: BTreeSet::new(),
+                attributes: Vec::new(),
+            };
+            for (column, cell) in header.iter().zip(record.iter()) {
+                match column {
+                    PATH_COLUMN => file.path = PathBuf::from(cell),
+                    TAGS_COLUMN => for tag in split_cell(cell) {
+                        add_parsed_tag(&mut file, Tag::new(&tag));
+                    },
+                    column => {
+                        let key = column.strip_suffix(ATTRIBUTE_MARKER).unwrap_or(column);
+                        for value in split_cell(cell) {
+                            add_parsed_tag(&mut file, Tag::new(&format!("{key}:{value}")));
+                        }
+                    },
+                }
+            }
+            files.push(file);
+        }
+        Ok(Self {
+            version: EXPORT_VERSION,
+            files,
+        })
+    }
+
+}
+
+impl Database {
+
+    /// Returns all files in the database sorted by path
+    pub fn export(&mut self) -> Result<Export, DatabaseError> {
+        let mut files = self.storage.get_all_files()?;
+        files.sort_by(super::sort_by_path);
+        Ok(Export {
+            version: EXPORT_VERSION,
+            files,
+        })
+    }
+
+    /// Import all files in `export`. Either every file is imported or none of them are.
+    pub fn import(&mut self, export: &Export, mode: ImportMode) -> Result<ImportReport, DatabaseError> {
+        if export.version > EXPORT_VERSION {
+            return Err(DatabaseError::UnsupportedExportVersion(export.version));
+        }
+        self.transaction(|database| {
+            let mut report = ImportReport::default();
+            for file in &export.files {
+                database.import_file(file, mode, &mut report)?;
+            }
+            Ok(report)
+        })
+    }
+
+    fn import_file(&mut self, file: &SearchResult, mode: ImportMode, report: &mut ImportReport) -> Result<(), DatabaseError> {
+        if file.path.as_os_str().is_empty() || !find::is_contained_relative_path(&file.path) {
+            report.invalid_paths.push(file.path.clone());
+            return Ok(());
+        }
+        let path = find::normalize_path(&file.path);
+        let mut allowed = SearchResult {
+            path: path.clone(),
+            tags: BTreeSet::new(),
+            attributes: Vec::new(),
+        };
+        for tag in &file.tags {
+            let parsed_tag = Tag::Key(tag.clone());
+            if self.config.tag_allowed(&parsed_tag) {
+                allowed.tags.insert(tag.clone());
+            } else {
+                report.disallowed_tags.push((path.clone(), parsed_tag));
+            }
+        }
+        for (key, value) in &file.attributes {
+            let parsed_tag = Tag::KeyValue { key: key.clone(), value: value.clone() };
+            if self.config.tag_allowed(&parsed_tag) {
+                allowed.attributes.push((key.clone(), value.clone()));
+            } else {
+                report.disallowed_tags.push((path.clone(), parsed_tag));
+            }
+        }
+        if self.storage.contains_file(&path)? {
+            let existing = self.storage.get_file_from_path(&path)?;
+            report.attribute_conflicts.extend(attribute_conflicts(&existing, &allowed));
+            if mode == ImportMode::Replace {
+                self.storage.clear_file(&path)?;
+            }
+        }
+        log::debug!("Importing {}", path.to_string_lossy().blue());
+        self.storage.add_file(&allowed)?;
+        report.imported += 1;
+        Ok(())
+    }
+
+}
+
+/// Returns the attributes in `imported` where `existing` has other values for the same key
+fn attribute_conflicts(existing: &SearchResult, imported: &SearchResult) -> Vec<AttributeConflict> {
+    let keys: BTreeSet<&String> = imported.attributes.iter()
+        .map(|(key, _)| key)
+        .collect();
+    keys.into_iter()
+        .filter_map(|key| {
+            let existing_values = attribute_values(existing, key);
+            let imported_values = attribute_values(imported, key);
+            if existing_values.is_empty() || existing_values == imported_values {
+                return None;
+            }
+            Some(AttributeConflict {
+                path: imported.path.clone(),
+                key: key.clone(),
+                existing: existing_values,
+                imported: imported_values,
+            })
+        })
+        .collect()
+}
+
+/// Returns the name of the column for attribute `key` in csv and tsv files
+fn attribute_column(key: &str) -> String {
+    match key {
+        PATH_COLUMN | TAGS_COLUMN => format!("{key}{ATTRIBUTE_MARKER}"),
+        key => key.to_string(),
+    }
+}
+
+/// Returns a cell with `parts` escaped and separated by lines
+fn join_cell<'a>(parts: impl IntoIterator<Item = &'a String>) -> String {
+    parts.into_iter()
+        .map(|part| part.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r"))
+        .collect::<Vec<_>>()
+        .join(&VALUE_SEPARATOR.to_string())
+}
+
+/// Returns the non-empty lines of `cell` with escaped characters restored.
+/// Spreadsheets may write `\r\n` line breaks, the `\r` is dropped.
+fn split_cell(cell: &str) -> impl Iterator<Item = String> + '_ {
+    cell.split(VALUE_SEPARATOR)
+        .map(|part| part.strip_suffix('\r').unwrap_or(part))
+        .filter(|part| !part.is_empty())
+        .map(unescape)
+}
+
+/// Restores the characters escaped by `join_cell`
+fn unescape(part: &str) -> String {
+    let mut unescaped = String::with_capacity(part.len());
+    let mut chars = part.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        // Backslashes not followed by an escaped character are kept as they are
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('\\') => unescaped.push('\\'),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            },
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+fn add_parsed_tag(file: &mut SearchResult, tag: Tag) {
+    match tag {
+        Tag::Key(tag) => {
+            file.tags.insert(tag);
+        },
+        Tag::KeyValue { key, value } => file.attributes.push((key, value)),
+    }
+}
+
+/// Returns all values of attribute `key` sorted
+fn attribute_values(file: &SearchResult, key: &str) -> Vec<String> {
+    let mut values: Vec<String> = file.attributes.iter()
+        .filter(|(x, _)| x == key)
+        .map(|(_, value)| value.clone())
+        .collect();
+    values.sort();
+    values
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SearchResult, AttributeConflict, Export, ExportFormat};
+    use std::path::PathBuf;
+
+    fn file(attributes: &[(&str, &str)]) -> SearchResult {
+        SearchResult {
+            path: PathBuf::from("file"),
+            tags: Default::default(),
+            attributes: attributes.iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn attribute_conflicts() {
+        let existing = file(&[("artist", "a"), ("year", "2000")]);
+        let imported = file(&[("artist", "b"), ("year", "2000"), ("title", "c")]);
+        assert_eq!(
+            super::attribute_conflicts(&existing, &imported),
+            vec![AttributeConflict {
+                path: PathBuf::from("file"),
+                key: "artist".to_string(),
+                existing: vec!["a".to_string()],
+                imported: vec!["b".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let mut original = file(&[("artist", "a, b"), ("time", "12:30"), ("genre", "x"), ("genre", "y")]);
+        original.tags.insert("tag_a".to_string());
+        original.tags.insert("tag_b".to_string());
+        let export = Export { version: super::EXPORT_VERSION, files: vec![original] };
+        let content = export.serialize(ExportFormat::Csv).unwrap();
+        let parsed = Export::parse(&content, ExportFormat::Csv).unwrap();
+        let mut attributes = parsed.files[0].attributes.clone();
+        attributes.sort();
+        assert_eq!(parsed.files[0].path, PathBuf::from("file"));
+        assert_eq!(parsed.files[0].tags, export.files[0].tags);
+        assert_eq!(attributes, vec![
+            ("artist".to_string(), "a, b".to_string()),
+            ("genre".to_string(), "x".to_string()),
+            ("genre".to_string(), "y".to_string()),
+            ("time".to_string(), "12:30".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn csv_round_trip_special_characters() {
+        let mut original = file(&[("comment", " padded "), ("lyrics", "line 1\nline 2\r\n"), ("dir", "C:\\new")]);
+        original.tags.insert("a,b".to_string());
+        original.tags.insert(" spaced ".to_string());
+        for format in [ExportFormat::Csv, ExportFormat::Tsv] {
+            let export = Export { version: super::EXPORT_VERSION, files: vec![original.clone()] };
+            let content = export.serialize(format).unwrap();
+            let parsed = Export::parse(&content, format).unwrap();
+            let mut attributes = parsed.files[0].attributes.clone();
+            attributes.sort();
+            let mut expected = original.attributes.clone();
+            expected.sort();
+            assert_eq!(parsed.files[0].tags, original.tags);
+            assert_eq!(attributes, expected);
+        }
+    }
+
+    #[test]
+    fn csv_attributes_named_like_columns() {
+        let original = file(&[("path", "elsewhere"), ("tags", "a")]);
+        let export = Export { version: super::EXPORT_VERSION, files: vec![original] };
+        let content = export.serialize(ExportFormat::Csv).unwrap();
+        let parsed = Export::parse(&content, ExportFormat::Csv).unwrap();
+        let mut attributes = parsed.files[0].attributes.clone();
+        attributes.sort();
+        assert_eq!(parsed.files[0].path, PathBuf::from("file"));
+        assert!(parsed.files[0].tags.is_empty());
+        assert_eq!(attributes, vec![
+            ("path".to_string(), "elsewhere".to_string()),
+            ("tags".to_string(), "a".to_string()),
+        ]);
+    }
+
+}
@@ -0,0 +1,146 @@
+use super::{Database, DatabaseError};
+use std::{
+    path::Path,
+    collections::BTreeMap,
+};
+
+/// Extended attribute with comma separated tags, used by freedesktop.org applications
+const TAGS_XATTR: &str = "user.xdg.tags";
+
+/// Prefix of extended attributes storing the values of a sea-serpent attribute
+const ATTRIBUTE_XATTR_PREFIX: &str = "user.sea-serpent.";
+
+/// Separates multiple values of the same attribute
+const VALUE_SEPARATOR: char = '\n';
+
+impl Database {
+
+    /// Write tags and attributes of `file` to its extended attributes.
+    /// Tags and attributes no longer in the database are removed from the extended attributes.
+    pub fn push_xattrs(&mut self, file: &Path) -> Result<(), DatabaseError> {
+        let data = self.get_file_info(file)?;
+        if data.tags.is_empty() {
+            remove_xattr(file, TAGS_XATTR)?;
+        } else {
+            let tags = data.tags.iter()
+                .map(|tag| tag.as_str())
+                .collect::<Vec<_>>()
+                .join(",");
+            write_xattr(file, TAGS_XATTR, &tags)?;
+        }
+        let mut attributes: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for (key, value) in &data.attributes {
+            attributes.entry(key.as_str()).or_default().push(value.as_str());
+        }
+        for key in attribute_keys(file)? {
+            if !attributes.contains_key(key.as_str()) {
+                remove_xattr(file, &format!("{ATTRIBUTE_XATTR_PREFIX}{key}"))?;
+            }
+        }
+        for (key, values) in attributes {
+            let value = values.join(&VALUE_SEPARATOR.to_string());
+            write_xattr(file, &format!("{ATTRIBUTE_XATTR_PREFIX}{key}"), &value)?;
+        }
+        Ok(())
+    }
+
+    /// Add tags and attributes stored in the extended attributes of `file` to the database.
+    /// Tags are added like with `add_tag`, so aliases, whitelist and blacklist apply.
+    pub fn pull_xattrs(&mut self, file: &Path) -> Result<(), DatabaseError> {
+        let mut tags = Vec::new();
+        if let Some(value) = read_xattr(file, TAGS_XATTR)? {
+            tags.extend(
+                value.split(',')
+                    .map(|tag| tag.trim())
+                    .filter(|tag| !tag.is_empty())
+                    .map(|tag| tag.to_string())
+            );
+        }
+        for key in attribute_keys(file)? {
+            if let Some(value) = read_xattr(file, &format!("{ATTRIBUTE_XATTR_PREFIX}{key}"))? {
+                tags.extend(
+                    value.split(VALUE_SEPARATOR)
+                        .filter(|value| !value.is_empty())
+                        .map(|value| format!("{key}:{value}"))
+                );
+            }
+        }
+        for tag in &tags {
+            self.add_tag(file, tag)?;
+        }
+        Ok(())
+    }
+
+}
+
+/// Returns the keys of all sea-serpent attributes stored in the extended attributes of `file`
+fn attribute_keys(file: &Path) -> Result<Vec<String>, DatabaseError> {
+    let keys = xattr::list(file)
+        .map_err(|_| DatabaseError::Xattr(file.to_path_buf()))?
+        .filter_map(|name| {
+            name.to_str()?
+                .strip_prefix(ATTRIBUTE_XATTR_PREFIX)
+                .map(|key| key.to_string())
+        })
+        .collect();
+    Ok(keys)
+}
+
+fn read_xattr(file: &Path, name: &str) -> Result<Option<String>, DatabaseError> {
+    let value = xattr::get(file, name)
+        .map_err(|_| DatabaseError::Xattr(file.to_path_buf()))?;
+    Ok(value.map(|value| String::from_utf8_lossy(&value).into_owned()))
+}
+
+fn write_xattr(file: &Path, name: &str, value: &str) -> Result<(), DatabaseError> {
+    xattr::set(file, name, value.as_bytes())
+        .map_err(|_| DatabaseError::Xattr(file.to_path_buf()))
+}
+
+/// Remove extended attribute `name` from `file` if it exists
+fn remove_xattr(file: &Path, name: &str) -> Result<(), DatabaseError> {
+    if read_xattr(file, name)?.is_none() {
+        return Ok(());
+    }
+    xattr::remove(file, name)
+        .map_err(|_| DatabaseError::Xattr(file.to_path_buf()))
+}
+
+#[cfg(test)]
+mod test {
+
+    #[test]
+    fn push_and_pull() {
+        let (dir, mut database) = super::super::create_test_database("xattr");
+        let file = dir.join("file");
+        std::fs::write(&file, "content").unwrap();
+        // Skip on filesystems without support for user extended attributes
+        if xattr::set(&file, "user.sea-serpent-test", b"").is_err() {
+            std::fs::remove_dir_all(&dir).unwrap();
+            return;
+        }
+        database.add_tag(&file, &"tag_a".to_string()).unwrap();
+        database.add_tag(&file, &"genre:a".to_string()).unwrap();
+        database.add_tag(&file, &"genre:b".to_string()).unwrap();
+        database.add_tag(&file, &"year:2000".to_string()).unwrap();
+        database.push_xattrs(&file).unwrap();
+        assert_eq!(super::read_xattr(&file, super::TAGS_XATTR).unwrap(), Some("tag_a".to_string()));
+        assert_eq!(super::read_xattr(&file, "user.sea-serpent.genre").unwrap(), Some("a\nb".to_string()));
+        // Attributes removed from the database are removed from the extended attributes
+        database.remove_attribute(&file, "year").unwrap();
+        database.push_xattrs(&file).unwrap();
+        assert_eq!(super::attribute_keys(&file).unwrap(), vec!["genre".to_string()]);
+        database.clear_file(&file).unwrap();
+        database.pull_xattrs(&file).unwrap();
+        let info = database.get_file_info(&file).unwrap();
+        let mut attributes = info.attributes.clone();
+        attributes.sort();
+        assert!(info.tags.contains("tag_a"));
+        assert_eq!(attributes, vec![
+            ("genre".to_string(), "a".to_string()),
+            ("genre".to_string(), "b".to_string()),
+        ]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+}
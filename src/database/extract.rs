@@ -0,0 +1,104 @@
+use super::{Database, DatabaseError, Tag, find};
+use crate::{extract::{CommandExtractor, Extractors}, template::{PathPattern, Template, TAG_FIELD}};
+use std::{path::Path, sync::Arc};
+use colored::Colorize;
+
+impl Database {
+
+    /// Returns the built-in extractors together with the external commands in the configuration.
+    /// Configured commands take precedence over built-in extractors for the same files.
+    pub fn extractors(&self) -> Extractors {
+        let mut extractors = Extractors::builtin(self.config.extract_keys());
+        for extractor_config in self.config.extractors() {
+            let (program, args) = match extractor_config.command.split_first() {
+                Some(command) => command,
+                None => continue,
+            };
+            let extractor = Arc::new(CommandExtractor::new(program.clone(), args.to_vec()));
+            for extension in &extractor_config.extensions {
+                extractors.register_extension(extension, extractor.clone());
+            }
+            for mime_type in &extractor_config.mime_types {
+                extractors.register_mime_type(mime_type, extractor.clone());
+            }
+        }
+        extractors
+    }
+
+    /// Read metadata from `file` with the configured extractors and add it as tags and attributes.
+    /// Returns the tags and attributes found.
+    pub fn extract_metadata(&mut self, file: &Path) -> Result<Vec<Tag>, DatabaseError> {
+        let extractors = self.extractors();
+        self.extract_with(file, &extractors)
+    }
+
+    /// Read metadata from `file` with `extractors` and add it as tags and attributes.
+    /// Returns the tags and attributes found.
+    pub fn extract_with(&mut self, file: &Path, extractors: &Extractors) -> Result<Vec<Tag>, DatabaseError> {
+        let mut tags = Vec::new();
+        for tag in extractors.extract(file)?.into_iter().filter_map(trim_tag) {
+            log::debug!("Extracted {} from {}", tag.to_string(), file.to_string_lossy().blue());
+            self.add_tag(file, &tag.to_string())?;
+            tags.push(tag);
+        }
+        Ok(tags)
+    }
+
+    /// Match the name of `file` without extension against `template` and add the fields as attributes.
+    /// The text matched by a `{#}` field is added as a tag.
+    /// Returns the tags and attributes added, or `None` if the name doesn't match.
+    pub fn extract_from_name(&mut self, file: &Path, template: &Template) -> Result<Option<Vec<Tag>>, DatabaseError> {
+        let fields = match file.file_stem().and_then(|stem| stem.to_str()) {
+            Some(stem) => template.match_text(stem),
+            None => None,
+        };
+        let fields = match fields {
+            Some(fields) => fields,
+            None => return Ok(None),
+        };
+        self.add_fields(file, fields).map(Some)
+    }
+
+    /// Match the path of `file` relative to the database root against `pattern` and add the fields
+    /// as attributes. The text matched by a `{#}` field is added as a tag.
+    /// Returns the tags and attributes added, or `None` if the path doesn't match.
+    pub fn tag_from_path(&mut self, file: &Path, pattern: &PathPattern) -> Result<Option<Vec<Tag>>, DatabaseError> {
+        let relative_path = find::path_relative_to_db_root(file, self.root_dir()?)?;
+        match pattern.match_path(&relative_path) {
+            Some(fields) => self.add_fields(file, fields).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn add_fields(&mut self, file: &Path, fields: Vec<(String, String)>) -> Result<Vec<Tag>, DatabaseError> {
+        let mut tags = Vec::new();
+        for tag in fields.into_iter().filter_map(field_tag) {
+            log::debug!("Adding {} from path of {}", tag.to_string(), file.to_string_lossy().blue());
+            self.add_tag(file, &tag.to_string())?;
+            tags.push(tag);
+        }
+        Ok(tags)
+    }
+
+}
+
+/// Returns the tag or attribute for a field matched by a template
+fn field_tag((key, value): (String, String)) -> Option<Tag> {
+    if key == TAG_FIELD {
+        trim_tag(Tag::Key(value))
+    } else {
+        trim_tag(Tag::KeyValue { key, value })
+    }
+}
+
+/// Trims whitespace from tags and attribute values. Returns `None` if nothing is left.
+fn trim_tag(tag: Tag) -> Option<Tag> {
+    let tag = match tag {
+        Tag::Key(tag) => Tag::Key(tag.trim().to_string()),
+        Tag::KeyValue { key, value } => Tag::KeyValue { key, value: value.trim().to_string() },
+    };
+    match &tag {
+        Tag::Key(key) | Tag::KeyValue { value: key, .. } if key.is_empty() => None,
+        _ => Some(tag),
+    }
+}
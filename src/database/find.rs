@@ -1,4 +1,4 @@
-use std::path::{PathBuf, Path};
+use std::path::{PathBuf, Path, Component};
 use super::DatabaseError;
 use colored::Colorize;
 
@@ -60,10 +60,58 @@ pub fn absolute_path(path: &Path) -> Result<PathBuf, DatabaseError> {
     if path.is_absolute() {
         return Ok(path.to_path_buf());
     }
-    let full_path =std::env::current_dir()
-        .or(Err(DatabaseError::CurrentDirNotFound))?
-        .join(path)
-        .components()
-        .collect();
+    let mut full_path = std::env::current_dir()
+        .or(Err(DatabaseError::CurrentDirNotFound))?;
+    // Resolve `..` so the path can be compared with the database root
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => { full_path.pop(); },
+            component => full_path.push(component),
+        }
+    }
     return Ok(full_path);
 }
+
+/// Returns `path` without redundant separators and `.` components.
+/// `..` is resolved against the component before it, unless it would leave the start of `path`.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir if matches!(normalized.components().next_back(), Some(Component::Normal(_))) => {
+                normalized.pop();
+            },
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Returns true if `path` is relative and does not point outside the directory it is relative to
+pub fn is_contained_relative_path(path: &Path) -> bool {
+    normalize_path(path).components()
+        .all(|component| matches!(component, Component::Normal(_)))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn normalize_path() {
+        assert_eq!(super::normalize_path(Path::new("./a//b/./c")), PathBuf::from("a/b/c"));
+        assert_eq!(super::normalize_path(Path::new("a/../b")), PathBuf::from("b"));
+        assert_eq!(super::normalize_path(Path::new("a/../../b")), PathBuf::from("../b"));
+    }
+
+    #[test]
+    fn contained_relative_path() {
+        assert!(super::is_contained_relative_path(Path::new("./a/b")));
+        assert!(super::is_contained_relative_path(Path::new("a/../b")));
+        assert!(!super::is_contained_relative_path(Path::new("/a/b")));
+        assert!(!super::is_contained_relative_path(Path::new("a/../../b")));
+    }
+
+}
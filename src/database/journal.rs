@@ -0,0 +1,71 @@
+use super::{Database, DatabaseError, find, storage::{Change, FileChange, JournalEntry}};
+use std::path::Path;
+use colored::Colorize;
+
+impl Database {
+
+    /// Run `f` and store all changes it makes to the database as a single operation in the journal.
+    /// Changes rolled back by a transaction inside `f` are not stored. If `f` fails halfway the
+    /// changes applied before the failure are stored, since changes on disk can't be rolled back.
+    /// Calls made while another operation is being recorded become part of that operation.
+    pub fn journaled<T, E, F>(&mut self, operation: &str, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Self) -> Result<T, E>,
+        E: From<DatabaseError>,
+    {
+        if self.storage.is_journaling() {
+            return f(self);
+        }
+        self.storage.start_journal();
+        let result = f(self);
+        self.storage.finish_journal(operation)?;
+        result
+    }
+
+    /// Returns the `limit` most recent operations in the journal, newest first
+    pub fn history(&mut self, limit: i64) -> Result<Vec<JournalEntry>, DatabaseError> {
+        self.storage.get_journal_entries(limit)
+    }
+
+    /// Returns all changes made to `file` that are stored in the journal, oldest first
+    pub fn file_changes(&mut self, file: &Path) -> Result<Vec<FileChange>, DatabaseError> {
+        let relative_path = find::path_relative_to_db_root(file, self.root_dir()?)?;
+        self.storage.get_file_changes(&relative_path)
+    }
+
+    /// Undo the most recent operation in the journal that has not been undone yet.
+    /// Files moved by the operation are moved back on disk. Deleted files are not restored on disk.
+    /// Returns the undone operation, or `None` if there is nothing to undo.
+    pub fn undo(&mut self) -> Result<Option<JournalEntry>, DatabaseError> {
+        let entry = match self.storage.get_last_journal_entry()? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let changes = self.storage.get_journal_changes(entry.id)?;
+        let root_dir = self.root_dir()?.to_path_buf();
+        for change in changes.iter().rev() {
+            if let Change::MoveFile { from, to } = change {
+                let original = root_dir.join(from);
+                let moved = root_dir.join(to);
+                // Only move files back that are still where the operation left them
+                if !moved.exists() || original.exists() {
+                    continue;
+                }
+                log::debug!(
+                    "Moving {} back to {}",
+                    moved.to_string_lossy().blue(),
+                    original.to_string_lossy().blue()
+                );
+                if let Some(parent) = original.parent() {
+                    std::fs::create_dir_all(parent)
+                        .map_err(|_| DatabaseError::WriteToDisk(parent.to_path_buf()))?;
+                }
+                std::fs::rename(&moved, &original)
+                    .map_err(|_| DatabaseError::WriteToDisk(original.clone()))?;
+            }
+        }
+        self.storage.undo_journal_entry(entry.id, &changes)?;
+        Ok(Some(entry))
+    }
+
+}
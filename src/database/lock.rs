@@ -0,0 +1,74 @@
+use super::DatabaseError;
+use fs2::FileExt;
+use std::{
+    fs::{File, OpenOptions},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// Name of lock file in database directory
+const LOCK_FILE: &str = "lock";
+
+/// Time between attempts to take a lock held by another process
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Advisory lock on a database. The lock is released when dropped.
+/// The operating system also releases it if the process is interrupted or killed.
+pub struct DatabaseLock {
+    file: File,
+}
+
+impl DatabaseLock {
+
+    /// Take the lock of the database in `database_path`.
+    /// Waits up to `timeout` milliseconds if another process holds the lock.
+    pub fn acquire(database_path: &Path, timeout: u64) -> Result<Self, DatabaseError> {
+        let path = database_path.join(LOCK_FILE);
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|_| DatabaseError::WriteToDisk(path.clone()))?;
+        let deadline = Instant::now() + Duration::from_millis(timeout);
+        // The lock file is left in place, since removing it would race with processes waiting for it
+        while file.try_lock_exclusive().is_err() {
+            if Instant::now() >= deadline {
+                return Err(DatabaseError::Locked(path));
+            }
+            std::thread::sleep(RETRY_INTERVAL);
+        }
+        log::debug!("Took database lock");
+        Ok(Self { file })
+    }
+
+}
+
+impl Drop for DatabaseLock {
+
+    fn drop(&mut self) {
+        if self.file.unlock().is_err() {
+            log::error!("Failed to release database lock");
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+
+    #[test]
+    fn lock_is_exclusive() {
+        let dir = std::env::temp_dir().join(format!("sea-serpent-lock-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let lock = super::DatabaseLock::acquire(&dir, 0).unwrap();
+        assert!(super::DatabaseLock::acquire(&dir, 0).is_err());
+        drop(lock);
+        assert!(super::DatabaseLock::acquire(&dir, 0).is_ok());
+        // A lock file left behind by an interrupted process doesn't block anyone
+        assert!(dir.join(super::LOCK_FILE).exists());
+        assert!(super::DatabaseLock::acquire(&dir, 0).is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+}
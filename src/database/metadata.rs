@@ -0,0 +1,283 @@
+use super::{Database, DatabaseError};
+use crate::utils::files::{FileSearchSettings, FiletypeFilter};
+use std::{
+    io,
+    fs::File,
+    path::{Path, PathBuf},
+    collections::HashSet,
+    time::UNIX_EPOCH,
+};
+use sha2::{Sha256, Digest};
+use colored::Colorize;
+
+/// Information used to recognize a file after it has been moved
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FileMetadata {
+    /// Sha256 hash of file content. Directories are not hashed.
+    pub hash: Option<String>,
+    /// Size of file in bytes
+    pub size: Option<i64>,
+    /// Last modification time in seconds since unix epoch
+    pub mtime: Option<i64>,
+    /// Id of device containing the file. Only available on unix.
+    pub device: Option<i64>,
+    /// Inode number of file. Only available on unix.
+    pub inode: Option<i64>,
+}
+
+impl FileMetadata {
+
+    /// Read size, modification time and inode of file on disk
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        let mtime = metadata.modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() as i64)
+            .ok();
+        let (device, inode) = device_and_inode(&metadata);
+        Ok(Self {
+            hash: None,
+            size: Some(metadata.len() as i64),
+            mtime,
+            device,
+            inode,
+        })
+    }
+
+    /// Returns true if `self` and `other` are the same inode on the same device.
+    /// Inodes can be reused after a file is deleted, so size and modification time
+    /// have to match as well. Directories change size and modification time with their
+    /// content and are compared on inode alone.
+    fn is_same_inode(&self, other: &FileMetadata, is_dir: bool) -> bool {
+        self.inode.is_some()
+            && self.device == other.device
+            && self.inode == other.inode
+            && (is_dir || (self.size == other.size && self.mtime == other.mtime))
+    }
+
+    /// Keep the content hash from `previous` if the size and modification time are unchanged
+    pub fn with_previous_hash(mut self, previous: &FileMetadata) -> Self {
+        if self.size == previous.size && self.mtime == previous.mtime {
+            self.hash = previous.hash.clone();
+        }
+        self
+    }
+
+    /// Add content hash of file in `path`.
+    /// The hash from `previous` is reused if the size and modification time are unchanged.
+    pub fn with_hash(mut self, path: &Path, previous: &FileMetadata) -> io::Result<Self> {
+        if !path.is_file() {
+            return Ok(self);
+        }
+        let unchanged = self.size == previous.size && self.mtime == previous.mtime;
+        self.hash = match &previous.hash {
+            Some(hash) if unchanged => Some(hash.clone()),
+            _ => Some(hash_file(path)?),
+        };
+        Ok(self)
+    }
+
+}
+
+#[cfg(unix)]
+fn device_and_inode(metadata: &std::fs::Metadata) -> (Option<i64>, Option<i64>) {
+    use std::os::unix::fs::MetadataExt;
+    (Some(metadata.dev() as i64), Some(metadata.ino() as i64))
+}
+
+#[cfg(not(unix))]
+fn device_and_inode(_metadata: &std::fs::Metadata) -> (Option<i64>, Option<i64>) {
+    (None, None)
+}
+
+/// Returns sha256 hash of file content as a hex string
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+impl Database {
+
+    /// Store size, modification time, inode and content hash of `file` if they have changed.
+    /// Files larger than the configured limit are hashed by `relink` instead, since hashing them is slow.
+    pub(super) fn update_file_metadata(&mut self, file: &Path, relative_path: &Path) -> Result<(), DatabaseError> {
+        let stored = self.storage.get_file_metadata(relative_path)?;
+        let hash_size_limit = self.config.hash_size_limit();
+        let current = FileMetadata::read(file)
+            .and_then(|metadata| match metadata.size {
+                Some(size) if size as u64 <= hash_size_limit => metadata.with_hash(file, &stored),
+                _ => Ok(metadata.with_previous_hash(&stored)),
+            });
+        match current {
+            Ok(current) if current != stored => self.storage.set_file_metadata(relative_path, &current),
+            Ok(_) => Ok(()),
+            Err(_) => {
+                log::debug!("Could not read metadata of {}", file.to_string_lossy().blue());
+                Ok(())
+            }
+        }
+    }
+
+    /// Find files that have been moved outside sea-serpent by comparing missing files with
+    /// untracked files in the database root. Files are matched by inode and, if `compare_content`
+    /// is true, by content hash. Tags of missing files are moved to the new path.
+    /// When comparing content, files still in place without an up to date hash, like files above
+    /// the hash size limit, are hashed first, so they can be found by content the next time they go missing.
+    /// Returns a list of original and new paths.
+    pub fn relink(&mut self, compare_content: bool) -> Result<Vec<(PathBuf, PathBuf)>, DatabaseError> {
+        if compare_content {
+            self.hash_tracked_files()?;
+        }
+        self.transaction(|database| database.relink_files(compare_content))
+    }
+
+    /// Store content hashes of tracked files that don't have an up to date one.
+    /// Runs outside of a transaction, so other processes aren't blocked while large files are hashed.
+    fn hash_tracked_files(&mut self) -> Result<(), DatabaseError> {
+        let root_dir = self.root_dir()?.to_path_buf();
+        for (path, stored) in self.storage.get_all_file_metadata()? {
+            let file = root_dir.join(&path);
+            let current = FileMetadata::read(&file)
+                .and_then(|metadata| metadata.with_hash(&file, &stored));
+            match current {
+                Ok(current) if current != stored => self.storage.set_file_metadata(&path, &current)?,
+                // Missing files are handled by relinking
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn relink_files(&mut self, compare_content: bool) -> Result<Vec<(PathBuf, PathBuf)>, DatabaseError> {
+        let root_dir = self.root_dir()?.to_path_buf();
+        let stored = self.storage.get_all_file_metadata()?;
+        let tracked: HashSet<PathBuf> = stored.iter()
+            .map(|(path, _)| path.clone())
+            .collect();
+        let mut missing: Vec<(PathBuf, FileMetadata)> = stored.into_iter()
+            .filter(|(path, metadata)| {
+                (metadata.inode.is_some() || metadata.hash.is_some()) && !root_dir.join(path).exists()
+            })
+            .collect();
+        if missing.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut untracked: Vec<(PathBuf, FileMetadata)> = self.untracked_files(&tracked)?
+            .into_iter()
+            .filter_map(|file| {
+                let metadata = FileMetadata::read(&file).ok()?;
+                Some((file, metadata))
+            })
+            .collect();
+        let mut matches = Vec::new();
+        // Comparing inodes is cheap, so all files are checked that way first
+        untracked.retain(|(file, current)| {
+            let position = missing.iter()
+                .position(|(_, stored)| stored.is_same_inode(current, file.is_dir()));
+            match position {
+                Some(index) => {
+                    let (original, stored) = missing.swap_remove(index);
+                    let metadata = FileMetadata { hash: stored.hash, ..current.clone() };
+                    matches.push((original, file.clone(), metadata));
+                    false
+                },
+                None => true,
+            }
+        });
+        if compare_content {
+            for (file, current) in untracked {
+                if missing.is_empty() {
+                    break;
+                }
+                // Only hash files that can match a missing file
+                let size_matches = missing.iter()
+                    .any(|(_, stored)| stored.hash.is_some() && stored.size == current.size);
+                if !size_matches {
+                    continue;
+                }
+                let current = match current.with_hash(&file, &FileMetadata::default()) {
+                    Ok(metadata) => metadata,
+                    Err(_) => continue,
+                };
+                let position = missing.iter()
+                    .position(|(_, stored)| stored.hash.is_some() && stored.hash == current.hash);
+                if let Some(index) = position {
+                    let (original, _) = missing.swap_remove(index);
+                    matches.push((original, file, current));
+                }
+            }
+        }
+        let mut relinked = Vec::new();
+        for (original, file, metadata) in matches {
+            let new_path = file.strip_prefix(&root_dir)
+                .map_err(|_| DatabaseError::FileNotFound(file.clone()))?
+                .to_path_buf();
+            log::debug!("Relinking {} to {}", original.to_string_lossy().blue(), new_path.to_string_lossy().blue());
+            self.storage.move_file(&original, new_path.clone())?;
+            self.storage.set_file_metadata(&new_path, &metadata)?;
+            relinked.push((original, new_path));
+        }
+        Ok(relinked)
+    }
+
+    /// Returns absolute paths of all files in the database root that are not in `tracked`
+    fn untracked_files(&self, tracked: &HashSet<PathBuf>) -> Result<Vec<PathBuf>, DatabaseError> {
+        let root_dir = self.root_dir()?;
+        let settings = FileSearchSettings {
+            recursive: true,
+            stdin: false,
+            filetype_filter: FiletypeFilter::All,
+        };
+        let files = crate::utils::files::get_files(&vec![root_dir.to_path_buf()], settings)
+            .into_iter()
+            .filter(|file| !file.starts_with(&self.path))
+            .filter(|file| {
+                file.strip_prefix(root_dir)
+                    .map(|relative| !tracked.contains(relative))
+                    .unwrap_or(false)
+            })
+            .collect();
+        Ok(files)
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+
+    #[test]
+    fn relink_by_hash() {
+        let (dir, mut database) = super::super::create_test_database("relink");
+        let original = dir.join("original");
+        std::fs::write(&original, "content").unwrap();
+        database.add_tag(&original, &"tag_a".to_string()).unwrap();
+        assert!(database.relink(true).unwrap().is_empty());
+        // Copying gives the file a new inode, so it can only be found by its content
+        let moved = dir.join("moved");
+        std::fs::copy(&original, &moved).unwrap();
+        std::fs::remove_file(&original).unwrap();
+        let relinked = database.relink(true).unwrap();
+        assert_eq!(relinked, vec![("original".into(), "moved".into())]);
+        assert!(database.get_file_info(&moved).unwrap().tags.contains("tag_a"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn relink_by_hash_from_tagging() {
+        let (dir, mut database) = super::super::create_test_database("relink-tagged");
+        let original = dir.join("original");
+        std::fs::write(&original, "content").unwrap();
+        database.add_tag(&original, &"tag_a".to_string()).unwrap();
+        // No relink has run since tagging, so the hash has to be stored when tagging
+        let moved = dir.join("moved");
+        std::fs::copy(&original, &moved).unwrap();
+        std::fs::remove_file(&original).unwrap();
+        let relinked = database.relink(true).unwrap();
+        assert_eq!(relinked, vec![("original".into(), "moved".into())]);
+        assert!(database.get_file_info(&moved).unwrap().tags.contains("tag_a"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+}
@@ -1,15 +1,37 @@
+mod check;
 mod config;
+mod copy;
 mod storage;
 mod error;
+mod export;
+#[cfg(unix)]
+mod extended_attributes;
+mod extract;
 mod find;
+mod journal;
+mod lock;
+mod metadata;
+mod organize;
+mod rename;
+mod sidecar;
 mod tag;
+mod tmsu;
+mod trash;
 
 use std::{path::{Path, PathBuf}, cmp::Ordering};
 use colored::Colorize;
+pub use check::CheckReport;
+pub use config::DeletionPolicy;
+pub use copy::CopyOptions;
 pub use find::find_database_from_current_dir;
 pub use error::DatabaseError;
-pub use storage::SearchResult;
+pub use export::{AttributeConflict, Export, ExportFormat, ImportMode, ImportReport};
+pub use lock::DatabaseLock;
+pub use rename::{ConflictStrategy, RenamePlan};
+pub use sidecar::SIDECAR_FILE;
+pub use storage::{Change, FileChange, JournalEntry, SearchResult};
 pub use tag::Tag;
+pub use tmsu::TmsuImport;
 
 /// Seaserpent database
 pub struct Database {
@@ -27,11 +49,15 @@ impl Database {
         let tags = self.config.get_alias(tag)
             .unwrap_or_else(|| vec![tag]);
         // Filter tags
-        let iter = tags
+        let allowed_tags = tags
             .iter()
             .map(|tag| Tag::new(tag))
-            .filter(|tag| self.config.tag_allowed(tag));
-        for tag in iter {
+            .filter(|tag| self.config.tag_allowed(tag))
+            .collect::<Vec<_>>();
+        if allowed_tags.is_empty() {
+            return Ok(());
+        }
+        for tag in allowed_tags {
             log::debug!("Adding tag, {}, to {}", tag.to_string(), file.display());
             // Add to storage
             let result = match tag {
@@ -45,7 +71,8 @@ impl Database {
             }
 
         }
-        Ok(())
+        // Used to find the file again if it is moved outside sea-serpent
+        self.update_file_metadata(file, &relative_path)
     }
 
     /// Remove tag from file
@@ -60,17 +87,68 @@ impl Database {
                 parsed_tag.to_string(),
                 file.to_string_lossy().blue()
             );
-            self.storage.remove_tag(&relative_path, &parsed_tag)?;
+            match &parsed_tag {
+                // `key:*` removes every value of `key`
+                Tag::KeyValue { key, value } if value == "*" =>
+                    self.storage.remove_attribute_key(&relative_path, key)?,
+                _ => self.storage.remove_tag(&relative_path, &parsed_tag)?,
+            }
         }
         Ok(())
     }
 
+    /// Remove all values of attribute `key` from file
+    pub fn remove_attribute(&mut self, file: &Path, key: &str) -> Result<(), DatabaseError> {
+        let relative_path = find::path_relative_to_db_root(file, self.root_dir()?)?;
+        log::debug!("Removing attribute {:?} from {}", key, file.to_string_lossy().blue());
+        self.storage.remove_attribute_key(&relative_path, key)
+    }
+
+    /// Remove all tags and attributes from file while keeping it in the database
+    pub fn clear_file(&mut self, file: &Path) -> Result<(), DatabaseError> {
+        let relative_path = find::path_relative_to_db_root(file, self.root_dir()?)?;
+        log::debug!("Removing all tags from {}", file.to_string_lossy().blue());
+        self.storage.clear_file(&relative_path)
+    }
+
+    /// Remove file with all its tags and attributes from the database.
+    /// Directories are removed together with all files below them, including files no longer on disk.
+    /// The files on disk are left untouched.
+    pub fn forget_file(&mut self, file: &Path) -> Result<(), DatabaseError> {
+        if !self.contains_files_below(file)? {
+            return Err(DatabaseError::FileNotFound(file.to_path_buf()));
+        }
+        let relative_path = find::path_relative_to_db_root(file, self.root_dir()?)?;
+        log::debug!("Removing {} from database", file.to_string_lossy().blue());
+        self.storage.remove_files_below(&relative_path)
+    }
+
+    /// Run `f` inside a single transaction.
+    /// Either all changes made by `f` are applied to the database or none of them are.
+    pub fn transaction<T, E, F>(&mut self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Self) -> Result<T, E>,
+        E: From<DatabaseError>,
+    {
+        storage::in_transaction(self, |database| &mut database.storage, f)
+    }
+
     /// Returns the root directory of the database
-    fn root_dir(&self) -> Result<&Path, DatabaseError> {
+    pub fn root_dir(&self) -> Result<&Path, DatabaseError> {
         self.path.parent()
             .ok_or(DatabaseError::RootDirNotFound)
     }
 
+    /// Returns true if `path` is inside the directory used to store the database itself
+    pub fn is_internal_path(&self, path: &Path) -> bool {
+        path.starts_with(&self.path)
+    }
+
+    /// Returns what to do when a file is deleted while watching the database
+    pub fn deletion_policy(&self) -> DeletionPolicy {
+        self.config.watch_deletions()
+    }
+
     /// Returns absolute path of a file in the database
     pub fn get_absolute_path(&self, file_path: &Path) -> Result<PathBuf, DatabaseError> {
         Ok(self.root_dir()?.join(file_path))
@@ -84,23 +162,33 @@ impl Database {
         let database_dir = path.join(find::DATABASE_DIR);
         std::fs::create_dir(&database_dir)
             .map_err(|_| DatabaseError::WriteToDisk(database_dir.clone()))?;
+        let config = config::DatabaseConfig::default();
         Ok(Self {
             path: find::get_full_path(&database_dir)?,
-            config: Default::default(),
-            storage: storage::DatabaseStorage::load(&database_dir)?,
+            storage: storage::DatabaseStorage::load(&database_dir, config.busy_timeout())?,
+            config,
         })
     }
 
     /// Load database from disk located in `path`
     pub fn load(path: PathBuf) -> Result<Self, DatabaseError> {
         log::debug!("Loading database from {}", path.to_string_lossy().blue());
+        let config = config::get_database_config(&path);
         Ok(Self {
-            config: config::get_database_config(&path),
-            storage: storage::DatabaseStorage::load(&path)?,
+            storage: storage::DatabaseStorage::load(&path, config.busy_timeout())?,
+            config,
             path: find::get_full_path(&path)?,
         })
     }
 
+    /// Take an advisory lock on the database to prevent other processes from running
+    /// write-heavy operations at the same time. The lock is released when dropped.
+    /// Every command changing the database takes it, except `watch` which runs until stopped
+    /// and only makes small changes.
+    pub fn lock(&self) -> Result<DatabaseLock, DatabaseError> {
+        DatabaseLock::acquire(&self.path, self.config.busy_timeout())
+    }
+
     /// Loads the database from the first ancestor with a existing database if any exist
     pub fn load_from_current_dir() -> Result<Self, DatabaseError> {
         let path = find_database_from_current_dir()?;
@@ -111,6 +199,10 @@ impl Database {
     /// - Remove files from database that does not exist anymore
     /// - Remove tags from that are not allowed (by whitelist or blacklist)
     pub fn cleanup(&mut self) -> Result<(), DatabaseError> {
+        self.transaction(Self::cleanup_files)
+    }
+
+    fn cleanup_files(&mut self) -> Result<(), DatabaseError> {
         // Remove files that does not exist
         let files_to_remove: Vec<PathBuf> = self.storage.get_all_files()?
             .into_iter()
@@ -123,7 +215,7 @@ impl Database {
             .map(|result| result.path)
             .collect();
         for file in files_to_remove {
-            log::debug!("Removing {} from database", file.to_string_lossy().blue());
+            log::info!("Removing {} from database", file.to_string_lossy().blue());
             self.storage.remove_file(&file)?;
         }
         // Remove tags that are not allowed
@@ -141,7 +233,7 @@ impl Database {
             .collect::<Vec<_>>();
         for (path, tags) in unallowed_tags {
             for tag in tags {
-                log::debug!("Removing {} from {}", tag.to_string(), path.to_string_lossy());
+                log::info!("Removing {} from {}", tag.to_string(), path.to_string_lossy());
                 self.storage.remove_tag(&path, &tag)?;
             }
         }
@@ -156,16 +248,43 @@ impl Database {
     }
 
     /// Move all data about `original_path` to `new_path`,
-    /// both in the database and on the filesystem
-    pub fn move_file(&mut self, original: &Path, new: &Path) -> Result<(), DatabaseError> {
+    /// both in the database and on the filesystem.
+    /// Directories are moved together with all files below them.
+    /// Fails if `new` already exists, unless `overwrite` is true.
+    pub fn move_file(&mut self, original: &Path, new: &Path, overwrite: bool) -> Result<(), DatabaseError> {
         let root_dir = self.root_dir()?;
-        let original_relative = find::path_relative_to_db_root(original, &root_dir)?;
-        // The file is moved here because `path_relative_to_db_root` requires the file to exist
-        // This can be fixed if. std::path::absolute comes out of nightly
+        let original_relative = find::path_relative_to_db_root(original, root_dir)?;
+        let new_relative = find::path_relative_to_db_root(new, root_dir)?;
+        if original_relative == new_relative {
+            return Ok(());
+        }
+        let destination_exists = new.exists() || self.storage.contains_file(&new_relative)?;
+        if destination_exists && !overwrite {
+            return Err(DatabaseError::DestinationExists(new.to_path_buf()));
+        }
         std::fs::rename(original, new)
             .map_err(|_| DatabaseError::WriteToDisk(new.to_path_buf()))?;
-        let new_relative = find::path_relative_to_db_root(new, &root_dir)?;
-        self.storage.move_file(&original_relative,new_relative)
+        self.transaction(|database| {
+            if destination_exists {
+                database.storage.remove_files_below(&new_relative)?;
+            }
+            database.storage.move_file(&original_relative, new_relative)
+        })
+    }
+
+    /// Move all data about `original` to `new` in the database.
+    /// Used when the file has already been moved on the filesystem.
+    pub fn track_move(&mut self, original: &Path, new: &Path) -> Result<(), DatabaseError> {
+        let root_dir = self.root_dir()?;
+        let original_relative = find::path_relative_to_db_root(original, root_dir)?;
+        let new_relative = find::path_relative_to_db_root(new, root_dir)?;
+        self.storage.move_file(&original_relative, new_relative)
+    }
+
+    /// Returns true if `path` or any file below it is in the database
+    pub fn contains_files_below(&mut self, path: &Path) -> Result<bool, DatabaseError> {
+        let relative_path = find::path_relative_to_db_root(path, self.root_dir()?)?;
+        Ok(!self.storage.get_files_below(&relative_path)?.is_empty())
     }
 
     /// Return file tags and attributes
@@ -199,3 +318,42 @@ pub fn sort_by_attribute(results: &mut Vec<SearchResult>, key: &str) {
 fn is_valid_init_dir(path: &Path) -> bool {
     path.is_dir() && !find::contains_database_dir(path)
 }
+
+/// Create an empty database in a new directory below the system temp dir.
+/// Returns the root directory of the database.
+#[cfg(test)]
+fn create_test_database(name: &str) -> (PathBuf, Database) {
+    let dir = std::env::temp_dir()
+        .join(format!("sea-serpent-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let dir = dir.canonicalize().unwrap();
+    let database = Database::init(&dir).unwrap();
+    (dir, database)
+}
+
+#[cfg(test)]
+mod test {
+
+    #[test]
+    fn forget_directory() {
+        let (dir, mut database) = super::create_test_database("forget-directory");
+        std::fs::create_dir(dir.join("a")).unwrap();
+        for file in ["a/x", "a/y", "b"] {
+            std::fs::write(dir.join(file), "").unwrap();
+            database.add_tag(&dir.join(file), &"tag".to_string()).unwrap();
+        }
+        // Files no longer on disk are forgotten as well
+        std::fs::remove_file(dir.join("a/y")).unwrap();
+        database.forget_file(&dir.join("a")).unwrap();
+        assert!(database.get_file_info(&dir.join("a/x")).is_err());
+        assert!(database.get_file_info(&dir.join("a/y")).is_err());
+        assert!(database.get_file_info(&dir.join("b")).is_ok());
+        assert!(matches!(
+            database.forget_file(&dir.join("a")),
+            Err(super::DatabaseError::FileNotFound(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+}
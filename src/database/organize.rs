@@ -0,0 +1,130 @@
+use super::{ConflictStrategy, Database, DatabaseError, RenamePlan};
+use std::path::{Path, PathBuf};
+use colored::Colorize;
+
+impl Database {
+
+    /// Returns the new paths of `files` formatted from their attributes with `template`.
+    /// The extension of every file is added to its new path. Files not in the database or already
+    /// at their new path are left out. New paths that are taken are handled with `on_conflict`.
+    pub fn plan_organize(&mut self, files: &[PathBuf], template: &str, on_conflict: ConflictStrategy) -> Result<RenamePlan, DatabaseError> {
+        let mut new_paths = Vec::new();
+        for file in files {
+            let file_info = match self.get_file_info(file) {
+                Ok(file_info) => file_info,
+                // Only files in the database have attributes to organize by
+                Err(DatabaseError::FileNotFound(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            let new_path = crate::format::format_result(&file_info, template)
+                .ok()
+                .map(|new_path| with_extension_of(PathBuf::from(new_path), file));
+            new_paths.push((file.clone(), new_path));
+        }
+        Ok(self.plan_moves(new_paths, on_conflict))
+    }
+
+    /// Move files as planned, creating missing directories. Nothing is moved if the plan has
+    /// conflicts and they should abort. If `remove_empty_dirs` is set, directories inside the
+    /// database root left empty by the moves are removed.
+    pub fn organize(&mut self, plan: &RenamePlan, remove_empty_dirs: bool) -> Result<(), DatabaseError> {
+        plan.check_conflicts()?;
+        for (original, new) in &plan.moves {
+            if let Some(parent) = new.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)
+                    .map_err(|_| DatabaseError::WriteToDisk(parent.to_path_buf()))?;
+            }
+            self.move_file(original, new, false)?;
+            log::info!("Moved {} to {}", original.display(), new.display());
+            if remove_empty_dirs {
+                self.remove_empty_parents(original)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the directories containing `file` as long as they are empty, stopping at the database root
+    fn remove_empty_parents(&self, file: &Path) -> Result<(), DatabaseError> {
+        let root_dir = self.root_dir()?.canonicalize()
+            .map_err(|_| DatabaseError::RootDirNotFound)?;
+        let mut dir = file.parent();
+        while let Some(current) = dir {
+            let inside_root = current.canonicalize()
+                .map(|path| path != root_dir && path.starts_with(&root_dir))
+                .unwrap_or(false);
+            // Removing fails for directories that aren't empty
+            if !inside_root || std::fs::remove_dir(current).is_err() {
+                break;
+            }
+            log::debug!("Removed empty directory {}", current.to_string_lossy().blue());
+            dir = current.parent();
+        }
+        Ok(())
+    }
+
+}
+
+/// Adds the extension of `file` to the file name of `path`
+fn with_extension_of(mut path: PathBuf, file: &Path) -> PathBuf {
+    if let Some(extension) = file.extension() {
+        let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".");
+        file_name.push(extension);
+        path.set_file_name(file_name);
+    }
+    path
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{ConflictStrategy, create_test_database};
+
+    #[test]
+    fn plan_organize() {
+        let (dir, mut database) = create_test_database("plan-organize");
+        let files = ["a.mp3", "b.mp3", "c.mp3", "untracked.mp3"].map(|name| dir.join(name));
+        for file in &files {
+            std::fs::write(file, "").unwrap();
+        }
+        database.add_tag(&files[0], &"artist:x".to_string()).unwrap();
+        database.add_tag(&files[0], &"title:y".to_string()).unwrap();
+        database.add_tag(&files[1], &"artist:x".to_string()).unwrap();
+        database.add_tag(&files[2], &"artist:x".to_string()).unwrap();
+        database.add_tag(&files[2], &"title:y".to_string()).unwrap();
+        let plan = database.plan_organize(&files, "{artist}/{title}", ConflictStrategy::Skip).unwrap();
+        assert_eq!(plan.moves, vec![(files[0].clone(), "x/y.mp3".into())]);
+        assert_eq!(plan.missing_attributes, vec![files[1].clone()]);
+        assert_eq!(plan.conflicts, vec![(files[2].clone(), "x/y.mp3".into())]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn organize_aborts_on_conflicts() {
+        let (dir, mut database) = create_test_database("organize-abort");
+        let files = ["a.mp3", "b.mp3"].map(|name| dir.join(name));
+        for file in &files {
+            std::fs::write(file, "").unwrap();
+            database.add_tag(file, &"artist:x".to_string()).unwrap();
+        }
+        let plan = database.plan_organize(&files, "{artist}", ConflictStrategy::Abort).unwrap();
+        assert!(database.organize(&plan, false).is_err());
+        assert!(files.iter().all(|file| file.exists()));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn remove_empty_parents() {
+        let (dir, database) = create_test_database("remove-empty-parents");
+        std::fs::create_dir_all(dir.join("a/b/c")).unwrap();
+        std::fs::write(dir.join("a/file"), "").unwrap();
+        database.remove_empty_parents(&dir.join("a/b/c/moved")).unwrap();
+        assert!(!dir.join("a/b").exists());
+        assert!(dir.join("a/file").exists());
+        std::fs::remove_file(dir.join("a/file")).unwrap();
+        database.remove_empty_parents(&dir.join("a/moved")).unwrap();
+        assert!(!dir.join("a").exists());
+        assert!(dir.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+}
@@ -0,0 +1,243 @@
+use super::{Database, DatabaseError, find};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// What to do with files whose new name is already taken
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ConflictStrategy {
+    /// Leave the file where it is
+    Skip,
+    /// Add ` (1)`, ` (2)`, ... to the new name until it is free
+    Suffix,
+    /// Don't rename any files
+    #[default]
+    Abort,
+}
+
+impl FromStr for ConflictStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(ConflictStrategy::Skip),
+            "suffix" => Ok(ConflictStrategy::Suffix),
+            "abort" => Ok(ConflictStrategy::Abort),
+            _ => Err(format!("Unknown conflict strategy {s}, expected skip, suffix or abort")),
+        }
+    }
+}
+
+/// Moves planned by `Database::plan_rename` or `Database::plan_organize`
+#[derive(Debug, Default)]
+pub struct RenamePlan {
+    /// Files with their new paths
+    pub moves: Vec<(PathBuf, PathBuf)>,
+    /// Files whose new path was already taken, with that path
+    pub conflicts: Vec<(PathBuf, PathBuf)>,
+    /// Files left where they are, since the template uses attributes they don't have
+    pub missing_attributes: Vec<PathBuf>,
+    /// How the conflicts were handled
+    pub on_conflict: ConflictStrategy,
+}
+
+impl RenamePlan {
+
+    /// Fails if the plan has conflicts and they should abort
+    pub(super) fn check_conflicts(&self) -> Result<(), DatabaseError> {
+        if self.on_conflict == ConflictStrategy::Abort && !self.conflicts.is_empty() {
+            return Err(DatabaseError::RenameConflicts(self.conflicts.len()));
+        }
+        Ok(())
+    }
+
+}
+
+impl Database {
+
+    /// Returns the new paths of `files` formatted from their attributes with `template`.
+    /// A new path is taken if it exists on disk or in the database, or if an earlier file in `files`
+    /// is renamed to it. Such conflicts are handled with `on_conflict`.
+    pub fn plan_rename(&mut self, files: &[PathBuf], template: &str, on_conflict: ConflictStrategy) -> Result<RenamePlan, DatabaseError> {
+        let mut new_paths = Vec::new();
+        for file in files {
+            let file_info = self.get_file_info(file)?;
+            let new_path = crate::format::format_result(&file_info, template)
+                .ok()
+                .map(PathBuf::from);
+            new_paths.push((file.clone(), new_path));
+        }
+        Ok(self.plan_moves(new_paths, on_conflict))
+    }
+
+    /// Plan moving files to their new paths, handling new paths that are taken with `on_conflict`.
+    /// Files without a new path are missing attributes used by the template.
+    pub(super) fn plan_moves(&mut self, new_paths: Vec<(PathBuf, Option<PathBuf>)>, on_conflict: ConflictStrategy) -> RenamePlan {
+        let mut plan = RenamePlan { on_conflict, ..Default::default() };
+        let mut taken = HashSet::new();
+        for (file, new_path) in new_paths {
+            let Some(new_path) = new_path else {
+                plan.missing_attributes.push(file);
+                continue;
+            };
+            if self.batch_key(&new_path) == self.batch_key(&file) {
+                continue;
+            }
+            let new_path = if self.is_path_taken(&new_path, &taken) {
+                plan.conflicts.push((file.clone(), new_path.clone()));
+                match on_conflict {
+                    ConflictStrategy::Suffix => self.free_path_with_suffix(&new_path, &taken),
+                    ConflictStrategy::Skip | ConflictStrategy::Abort => continue,
+                }
+            } else {
+                new_path
+            };
+            taken.insert(self.batch_key(&new_path));
+            plan.moves.push((file, new_path));
+        }
+        plan
+    }
+
+    /// Rename files as planned. Nothing is renamed if the plan has conflicts and they should abort.
+    pub fn rename_files(&mut self, plan: &RenamePlan) -> Result<(), DatabaseError> {
+        plan.check_conflicts()?;
+        for (original, new) in &plan.moves {
+            self.move_file(original, new, false)?;
+            log::info!("Moved {} to {}", original.display(), new.display());
+        }
+        Ok(())
+    }
+
+    /// Returns `path` in the form used to compare paths within a batch of renames
+    fn batch_key(&self, path: &Path) -> PathBuf {
+        self.root_dir()
+            .and_then(|root_dir| find::path_relative_to_db_root(path, root_dir))
+            .unwrap_or_else(|_| find::normalize_path(path))
+    }
+
+    fn is_path_taken(&mut self, path: &Path, taken: &HashSet<PathBuf>) -> bool {
+        path.exists() || taken.contains(&self.batch_key(path)) || self.get_file_info(path).is_ok()
+    }
+
+    /// Returns `path` with the first free ` (n)` suffix added before the extension
+    fn free_path_with_suffix(&mut self, path: &Path, taken: &HashSet<PathBuf>) -> PathBuf {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let extension = path.extension()
+            .map(|extension| format!(".{}", extension.to_string_lossy()))
+            .unwrap_or_default();
+        let mut number = 1;
+        loop {
+            let candidate = path.with_file_name(format!("{stem} ({number}){extension}"));
+            if !self.is_path_taken(&candidate, taken) {
+                return candidate;
+            }
+            number += 1;
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use super::ConflictStrategy;
+    use std::{path::{Path, PathBuf}, str::FromStr};
+
+    /// Create tracked files in `dir` with attribute `name` set to the given value
+    fn create_files(database: &mut super::Database, dir: &Path, files: &[(&str, &str)]) -> Vec<PathBuf> {
+        files.iter()
+            .map(|(file, name)| {
+                let file = dir.join(file);
+                std::fs::write(&file, "").unwrap();
+                database.add_tag(&file, &format!("name:{name}")).unwrap();
+                file
+            })
+            .collect()
+    }
+
+    #[test]
+    fn conflicts_in_batch() {
+        let (dir, mut database) = super::super::create_test_database("rename-batch");
+        let files = create_files(&mut database, &dir, &[("a", "x"), ("b", "x"), ("c", "y")]);
+        let template = format!("{}/{{name}}", dir.display());
+        let plan = database.plan_rename(&files, &template, ConflictStrategy::Skip).unwrap();
+        assert_eq!(plan.moves, vec![
+            (files[0].clone(), dir.join("x")),
+            (files[2].clone(), dir.join("y")),
+        ]);
+        assert_eq!(plan.conflicts, vec![(files[1].clone(), dir.join("x"))]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn conflicts_on_disk_and_in_database() {
+        let (dir, mut database) = super::super::create_test_database("rename-taken");
+        let files = create_files(&mut database, &dir, &[("a", "on_disk"), ("b", "in_db")]);
+        std::fs::write(dir.join("on_disk"), "").unwrap();
+        // Tracked files that were removed from disk still take their path
+        std::fs::write(dir.join("in_db"), "").unwrap();
+        database.add_tag(&dir.join("in_db"), &"tag".to_string()).unwrap();
+        std::fs::remove_file(dir.join("in_db")).unwrap();
+        let template = format!("{}/{{name}}", dir.display());
+        let plan = database.plan_rename(&files, &template, ConflictStrategy::Skip).unwrap();
+        assert!(plan.moves.is_empty());
+        assert_eq!(plan.conflicts, vec![
+            (files[0].clone(), dir.join("on_disk")),
+            (files[1].clone(), dir.join("in_db")),
+        ]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn suffix_numbering() {
+        let (dir, mut database) = super::super::create_test_database("rename-suffix");
+        let files = create_files(&mut database, &dir, &[("a", "x"), ("b", "x"), ("c", "x")]);
+        std::fs::write(dir.join("x.mp3"), "").unwrap();
+        let template = format!("{}/{{name}}.mp3", dir.display());
+        let plan = database.plan_rename(&files, &template, ConflictStrategy::Suffix).unwrap();
+        assert_eq!(plan.moves, vec![
+            (files[0].clone(), dir.join("x (1).mp3")),
+            (files[1].clone(), dir.join("x (2).mp3")),
+            (files[2].clone(), dir.join("x (3).mp3")),
+        ]);
+        database.rename_files(&plan).unwrap();
+        assert!(dir.join("x (3).mp3").exists());
+        assert!(!files[2].exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn abort_leaves_files() {
+        let (dir, mut database) = super::super::create_test_database("rename-abort");
+        let files = create_files(&mut database, &dir, &[("a", "x"), ("b", "x")]);
+        let template = format!("{}/{{name}}", dir.display());
+        let plan = database.plan_rename(&files, &template, ConflictStrategy::Abort).unwrap();
+        assert!(database.rename_files(&plan).is_err());
+        assert!(files.iter().all(|file| file.exists()));
+        assert!(!dir.join("x").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_attributes() {
+        let (dir, mut database) = super::super::create_test_database("rename-missing");
+        let file = dir.join("a");
+        std::fs::write(&file, "").unwrap();
+        database.add_tag(&file, &"tag".to_string()).unwrap();
+        let template = format!("{}/{{name}}", dir.display());
+        let plan = database.plan_rename(std::slice::from_ref(&file), &template, ConflictStrategy::Abort).unwrap();
+        assert!(plan.moves.is_empty());
+        assert_eq!(plan.missing_attributes, vec![file]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn conflict_strategy_from_str() {
+        assert_eq!(ConflictStrategy::from_str("skip"), Ok(ConflictStrategy::Skip));
+        assert_eq!(ConflictStrategy::from_str("suffix"), Ok(ConflictStrategy::Suffix));
+        assert_eq!(ConflictStrategy::from_str("abort"), Ok(ConflictStrategy::Abort));
+        assert!(ConflictStrategy::from_str("overwrite").is_err());
+    }
+
+}
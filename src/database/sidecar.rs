@@ -0,0 +1,183 @@
+use super::{Database, DatabaseError, Export, ImportMode, ImportReport, SearchResult, find, export::EXPORT_VERSION};
+use crate::utils::files::{FileSearchSettings, FiletypeFilter};
+use std::{
+    path::{Path, PathBuf},
+    collections::{BTreeMap, BTreeSet},
+};
+use colored::Colorize;
+
+/// Name of sidecar files
+pub const SIDECAR_FILE: &str = ".tags.toml";
+
+/// Tags and attributes of a single file in a sidecar
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+struct SidecarEntry {
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    tags: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    attributes: BTreeMap<String, Vec<String>>,
+}
+
+/// Content of a sidecar file with entries by file name
+type Sidecar = BTreeMap<String, SidecarEntry>;
+
+impl Database {
+
+    /// Write a sidecar file to every directory below `path` that contains files in the database.
+    /// Sidecars of directories without files in the database are removed.
+    /// Returns the paths of the written sidecars.
+    pub fn write_sidecars(&mut self, path: &Path) -> Result<Vec<PathBuf>, DatabaseError> {
+        let root_dir = self.root_dir()?.to_path_buf();
+        // Found sidecars are compared with the database root, so they need absolute paths
+        let path = find::absolute_path(path)?;
+        let relative_path = find::path_relative_to_db_root(&path, &root_dir)?;
+        let mut sidecars: BTreeMap<PathBuf, Sidecar> = BTreeMap::new();
+        for file in self.storage.get_files_below_with_data(&relative_path)? {
+            let (directory, name) = match (file.path.parent(), file.path.file_name()) {
+                (Some(directory), Some(name)) => (directory.to_path_buf(), name.to_string_lossy().to_string()),
+                _ => continue,
+            };
+            // The sidecar of the parent of `path` would only contain some of its files
+            if !directory.starts_with(&relative_path) {
+                continue;
+            }
+            sidecars.entry(directory)
+                .or_default()
+                .insert(name, SidecarEntry::from(file));
+        }
+        for sidecar_path in find_sidecars(&path) {
+            let directory = sidecar_path.parent()
+                .and_then(|directory| directory.strip_prefix(&root_dir).ok());
+            if let Some(directory) = directory {
+                if !sidecars.contains_key(directory) {
+                    log::debug!("Removing {}", sidecar_path.to_string_lossy().blue());
+                    std::fs::remove_file(&sidecar_path)
+                        .map_err(|_| DatabaseError::WriteToDisk(sidecar_path.clone()))?;
+                }
+            }
+        }
+        let mut written = Vec::new();
+        for (directory, sidecar) in sidecars {
+            let directory = root_dir.join(directory);
+            // Files in the database can be missing on disk
+            if !directory.is_dir() {
+                continue;
+            }
+            let sidecar_path = directory.join(SIDECAR_FILE);
+            let content = toml::to_string(&sidecar)
+                .map_err(|_| DatabaseError::WriteToDisk(sidecar_path.clone()))?;
+            std::fs::write(&sidecar_path, content)
+                .map_err(|_| DatabaseError::WriteToDisk(sidecar_path.clone()))?;
+            written.push(sidecar_path);
+        }
+        Ok(written)
+    }
+
+    /// Import tags and attributes from all sidecar files below `path`
+    pub fn read_sidecars(&mut self, path: &Path, mode: ImportMode) -> Result<ImportReport, DatabaseError> {
+        let root_dir = self.root_dir()?.to_path_buf();
+        let mut files = Vec::new();
+        for sidecar_path in find_sidecars(path) {
+            let directory = sidecar_path.parent()
+                .ok_or_else(|| DatabaseError::FileNotFound(sidecar_path.clone()))?;
+            let directory = find::path_relative_to_db_root(directory, &root_dir)?;
+            let content = std::fs::read_to_string(&sidecar_path)
+                .map_err(|_| DatabaseError::ReadFromDisk(sidecar_path.clone()))?;
+            let sidecar: Sidecar = toml::from_str(&content)
+                .map_err(|_| DatabaseError::InvalidSidecar(sidecar_path.clone()))?;
+            for (name, entry) in sidecar {
+                files.push(entry.into_search_result(directory.join(name)));
+            }
+        }
+        self.import(&Export { version: EXPORT_VERSION, files }, mode)
+    }
+
+}
+
+impl From<SearchResult> for SidecarEntry {
+    fn from(file: SearchResult) -> Self {
+        let mut attributes: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (key, value) in file.attributes {
+            attributes.entry(key).or_default().push(value);
+        }
+        Self {
+            tags: file.tags,
+            attributes,
+        }
+    }
+}
+
+impl SidecarEntry {
+
+    fn into_search_result(self, path: PathBuf) -> SearchResult {
+        let attributes = self.attributes.into_iter()
+            .flat_map(|(key, values)| values.into_iter().map(move |value| (key.clone(), value)))
+            .collect();
+        SearchResult {
+            path,
+            tags: self.tags,
+            attributes,
+        }
+    }
+
+}
+
+/// Returns all sidecar files below `path`
+fn find_sidecars(path: &Path) -> Vec<PathBuf> {
+    let settings = FileSearchSettings {
+        recursive: true,
+        stdin: false,
+        filetype_filter: FiletypeFilter::FilesOnly,
+    };
+    crate::utils::files::get_files(&vec![path.to_path_buf()], settings)
+        .into_iter()
+        .filter(|file| file.file_name().is_some_and(|name| name == SIDECAR_FILE))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Sidecar, SidecarEntry, SearchResult};
+    use std::path::{Component, PathBuf};
+
+    #[test]
+    fn sidecar_round_trip() {
+        let file = SearchResult {
+            path: PathBuf::from("dir/file"),
+            tags: ["tag_a".to_string()].into_iter().collect(),
+            attributes: vec![("artist".to_string(), "a".to_string()), ("artist".to_string(), "b".to_string())],
+        };
+        let mut sidecar = Sidecar::new();
+        sidecar.insert("file".to_string(), SidecarEntry::from(file));
+        let content = toml::to_string(&sidecar).unwrap();
+        let mut parsed: Sidecar = toml::from_str(&content).unwrap();
+        let result = parsed.remove("file").unwrap().into_search_result(PathBuf::from("dir/file"));
+        assert_eq!(result.path, PathBuf::from("dir/file"));
+        assert!(result.tags.contains("tag_a"));
+        assert_eq!(result.attributes.len(), 2);
+    }
+
+    #[test]
+    fn remove_stale_sidecars() {
+        let (dir, mut database) = super::super::create_test_database("sidecar-stale");
+        for subdir in ["a", "b"] {
+            std::fs::create_dir(dir.join(subdir)).unwrap();
+            std::fs::write(dir.join(subdir).join("file"), "").unwrap();
+            database.add_tag(&dir.join(subdir).join("file"), &"tag".to_string()).unwrap();
+        }
+        assert_eq!(database.write_sidecars(&dir).unwrap().len(), 2);
+        database.forget_file(&dir.join("b/file")).unwrap();
+        // Sidecars are found with relative paths too, like the default `.`
+        let relative_dir: PathBuf = std::env::current_dir().unwrap()
+            .components()
+            .skip(1)
+            .map(|_| Component::ParentDir)
+            .chain(dir.components().skip(1))
+            .collect();
+        assert_eq!(database.write_sidecars(&relative_dir).unwrap(), vec![dir.join("a").join(super::SIDECAR_FILE)]);
+        assert!(dir.join("a").join(super::SIDECAR_FILE).exists());
+        assert!(!dir.join("b").join(super::SIDECAR_FILE).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+}
@@ -0,0 +1,302 @@
+use super::{DatabaseStorage, SearchResult, models, rebase_path};
+use crate::database::{DatabaseError, Tag};
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+use diesel::prelude::*;
+
+
+/// A single change made to the database.
+/// Changes are stored in the journal so they can be undone later.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Change {
+    /// File was added to the database
+    AddFile { path: PathBuf },
+    /// Tag was added to file
+    AddTag { path: PathBuf, tag: String },
+    /// Tag was removed from file
+    RemoveTag { path: PathBuf, tag: String },
+    /// Attribute was added to file
+    AddAttribute { path: PathBuf, key: String, value: String },
+    /// Attribute was removed from file
+    RemoveAttribute { path: PathBuf, key: String, value: String },
+    /// File was removed from the database together with the tags and attributes it had
+    RemoveFile { file: SearchResult },
+    /// File or directory, including all files below it, was moved from `from` to `to`
+    MoveFile { from: PathBuf, to: PathBuf },
+}
+
+
+/// Operation stored in the journal
+pub struct JournalEntry {
+    pub id: i32,
+    /// Name of the operation
+    pub operation: String,
+    /// Unix timestamp of when the operation was run
+    pub timestamp: i64,
+    /// True if the operation has been undone
+    pub undone: bool,
+    /// User that ran the operation
+    pub user: Option<String>,
+    /// Number of changes made by the operation
+    pub changes: i64,
+}
+
+
+/// Change made to a single file, together with the operation that made it
+pub struct FileChange {
+    /// Name of the operation
+    pub operation: String,
+    /// Unix timestamp of when the operation was run
+    pub timestamp: i64,
+    /// User that ran the operation
+    pub user: Option<String>,
+    /// True if the operation has been undone
+    pub undone: bool,
+    /// Change made to the file. Moves of a directory are shown as moves of the file itself.
+    pub change: Change,
+}
+
+
+/// Returns the name of the user running sea-serpent
+fn current_user() -> Option<String> {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+}
+
+
+impl Change {
+
+    /// Returns the path of the file changed. For moves this is the original path.
+    pub fn path(&self) -> &Path {
+        match self {
+            Change::AddFile { path } => path,
+            Change::AddTag { path, .. } => path,
+            Change::RemoveTag { path, .. } => path,
+            Change::AddAttribute { path, .. } => path,
+            Change::RemoveAttribute { path, .. } => path,
+            Change::RemoveFile { file } => &file.path,
+            Change::MoveFile { from, .. } => from,
+        }
+    }
+
+    /// Returns the new path of moved files
+    pub fn new_path(&self) -> Option<&Path> {
+        match self {
+            Change::MoveFile { to, .. } => Some(to),
+            _ => None,
+        }
+    }
+
+}
+
+
+impl DatabaseStorage {
+
+    /// Start recording changes made to the database
+    pub fn start_journal(&mut self) {
+        self.journal = Some(Vec::new());
+    }
+
+
+    /// Returns true if changes are being recorded
+    pub fn is_journaling(&self) -> bool {
+        self.journal.is_some()
+    }
+
+
+    /// Record `change` if changes are being recorded
+    pub(super) fn record(&mut self, change: Change) {
+        if let Some(journal) = &mut self.journal {
+            journal.push(change);
+        }
+    }
+
+
+    /// Stop recording changes and store the recorded changes in the journal as `operation`.
+    /// Nothing is stored if no changes were made.
+    pub fn finish_journal(&mut self, operation: &str) -> Result<(), DatabaseError> {
+        let changes = self.journal.take().unwrap_or_default();
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let timestamp = chrono::Utc::now().timestamp();
+        self.transaction(|storage| {
+            let journal_id = diesel::insert_into(models::journal::table)
+                .values((
+                    models::journal::operation.eq(operation),
+                    models::journal::timestamp.eq(timestamp),
+                    models::journal::user.eq(current_user()),
+                ))
+                .returning(models::journal::id)
+                .get_result::<i32>(&mut storage.connection)?;
+            for (position, change) in changes.iter().enumerate() {
+                let journal_change = models::JournalChange {
+                    journal_id,
+                    position: position as i32,
+                    path: change.path().to_string_lossy().to_string(),
+                    new_path: change.new_path().map(|path| path.to_string_lossy().to_string()),
+                    change: serde_json::to_string(change)?,
+                };
+                diesel::insert_into(models::journal_changes::table)
+                    .values(&journal_change)
+                    .execute(&mut storage.connection)?;
+            }
+            Ok(())
+        })
+    }
+
+
+    /// Returns the `limit` most recent operations in the journal, newest first
+    pub fn get_journal_entries(&mut self, limit: i64) -> Result<Vec<JournalEntry>, DatabaseError> {
+        models::journal::table
+            .order(models::journal::id.desc())
+            .limit(limit)
+            .load::<models::JournalEntry>(&mut self.connection)?
+            .into_iter()
+            .map(|entry| self.journal_entry_from_model(entry))
+            .collect()
+    }
+
+
+    /// Returns the most recent operation in the journal that has not been undone
+    pub fn get_last_journal_entry(&mut self) -> Result<Option<JournalEntry>, DatabaseError> {
+        models::journal::table
+            .filter(models::journal::undone.eq(false))
+            .order(models::journal::id.desc())
+            .first::<models::JournalEntry>(&mut self.connection)
+            .optional()?
+            .map(|entry| self.journal_entry_from_model(entry))
+            .transpose()
+    }
+
+
+    fn journal_entry_from_model(&mut self, entry: models::JournalEntry) -> Result<JournalEntry, DatabaseError> {
+        let changes = models::journal_changes::table
+            .filter(models::journal_changes::journal_id.eq(entry.id))
+            .count()
+            .get_result::<i64>(&mut self.connection)?;
+        Ok(JournalEntry {
+            id: entry.id,
+            operation: entry.operation,
+            timestamp: entry.timestamp,
+            undone: entry.undone,
+            user: entry.user,
+            changes,
+        })
+    }
+
+
+    /// Returns all changes made by operation `journal_id` in the order they were made
+    pub fn get_journal_changes(&mut self, journal_id: i32) -> Result<Vec<Change>, DatabaseError> {
+        models::journal_changes::table
+            .filter(models::journal_changes::journal_id.eq(journal_id))
+            .order(models::journal_changes::position)
+            .select(models::journal_changes::change)
+            .load::<String>(&mut self.connection)?
+            .iter()
+            .map(|change| Ok(serde_json::from_str(change)?))
+            .collect()
+    }
+
+
+    /// Returns all changes made to `path`, oldest first.
+    /// Moves are followed backwards, so changes made before the file was moved to `path` are included.
+    pub fn get_file_changes(&mut self, path: &Path) -> Result<Vec<FileChange>, DatabaseError> {
+        // Find all paths the file has had, so only changes to those paths have to be loaded
+        let moves = models::journal_changes::table
+            .filter(models::journal_changes::new_path.is_not_null())
+            .order((models::journal_changes::journal_id.desc(), models::journal_changes::position.desc()))
+            .select((models::journal_changes::path, models::journal_changes::new_path))
+            .load::<(String, Option<String>)>(&mut self.connection)?;
+        let mut current_path = path.to_path_buf();
+        let mut paths = HashSet::from([current_path.to_string_lossy().to_string()]);
+        for (from, to) in moves {
+            let to = PathBuf::from(to.unwrap_or_default());
+            if let Some(original) = rebase_path(&current_path, &to, Path::new(&from)) {
+                paths.insert(original.to_string_lossy().to_string());
+                current_path = original;
+            }
+        }
+        let rows = models::journal_changes::table
+            .inner_join(models::journal::table)
+            .filter(
+                models::journal_changes::new_path.is_not_null()
+                    .or(models::journal_changes::path.eq_any(paths))
+            )
+            .order((models::journal_changes::journal_id.desc(), models::journal_changes::position.desc()))
+            .select((
+                models::journal::operation,
+                models::journal::timestamp,
+                models::journal::user,
+                models::journal::undone,
+                models::journal_changes::change,
+            ))
+            .load::<(String, i64, Option<String>, bool, String)>(&mut self.connection)?;
+        let mut current_path = path.to_path_buf();
+        let mut file_changes = Vec::new();
+        for (operation, timestamp, user, undone, change) in rows {
+            let change = match serde_json::from_str::<Change>(&change)? {
+                Change::MoveFile { from, to } => match rebase_path(&current_path, &to, &from) {
+                    Some(original) => {
+                        let to = std::mem::replace(&mut current_path, original.clone());
+                        Change::MoveFile { from: original, to }
+                    },
+                    None => continue,
+                },
+                change if change.path() == current_path => change,
+                _ => continue,
+            };
+            file_changes.push(FileChange { operation, timestamp, user, undone, change });
+        }
+        file_changes.reverse();
+        Ok(file_changes)
+    }
+
+
+    /// Revert `changes` made by operation `journal_id` and mark the operation as undone.
+    /// Files on disk are not touched.
+    pub fn undo_journal_entry(&mut self, journal_id: i32, changes: &[Change]) -> Result<(), DatabaseError> {
+        // Reverting changes should not be recorded as new changes
+        let journal = self.journal.take();
+        let result = self.transaction(|storage| {
+            for change in changes.iter().rev() {
+                storage.revert_change(change)?;
+            }
+            diesel::update(models::journal::table)
+                .filter(models::journal::id.eq(journal_id))
+                .set(models::journal::undone.eq(true))
+                .execute(&mut storage.connection)?;
+            Ok(())
+        });
+        self.journal = journal;
+        result
+    }
+
+
+    fn revert_change(&mut self, change: &Change) -> Result<(), DatabaseError> {
+        let result = match change {
+            Change::AddFile { path } => self.remove_file(path),
+            Change::AddTag { path, tag } => self.remove_tag(path, &Tag::Key(tag.clone())),
+            Change::RemoveTag { path, tag } => self.add_tag(path, tag),
+            Change::AddAttribute { path, key, value } => {
+                let tag = Tag::KeyValue { key: key.clone(), value: value.clone() };
+                self.remove_tag(path, &tag)
+            },
+            Change::RemoveAttribute { path, key, value } =>
+                self.add_attribute(path, key.clone(), value.clone()),
+            Change::RemoveFile { file } => self.add_file(file),
+            Change::MoveFile { from, to } => self.move_file(to, from.clone()),
+        };
+        match result {
+            // The file may already be gone if it was removed by a later operation
+            Err(DatabaseError::FileNotFound(_)) => Ok(()),
+            result => result,
+        }
+    }
+
+}
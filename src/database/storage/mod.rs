@@ -1,12 +1,17 @@
+mod journal;
 mod models;
+mod tmsu;
 
-use super::{DatabaseError, Tag};
+pub use journal::{Change, FileChange, JournalEntry};
+pub use tmsu::read_tmsu_database;
+
+use super::{DatabaseError, Tag, metadata::FileMetadata};
 use crate::search::{SearchExpression, UnaryOp, BinaryOp};
 
 use std::{
     str::FromStr,
     path::{Path, PathBuf},
-    collections::HashSet,
+    collections::BTreeSet,
 };
 use diesel::{
     self,
@@ -15,6 +20,7 @@ use diesel::{
     result::Error as DieselError,
     result::DatabaseErrorKind,
     Connection, RunQueryDsl, QueryDsl,
+    connection::{SimpleConnection, TransactionManager, AnsiTransactionManager},
 };
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness,};
 
@@ -29,38 +35,136 @@ const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
 pub struct DatabaseStorage {
     /// Connection to sqlite database
     connection: SqliteConnection,
+    /// Changes recorded for the journal, if recording
+    journal: Option<Vec<Change>>,
+    /// Number of recorded changes when each open transaction began
+    journal_marks: Vec<usize>,
 }
 
 
-#[derive(serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct SearchResult {
     pub path: PathBuf,
-    pub tags: HashSet<String>,
+    pub tags: BTreeSet<String>,
     pub attributes: Vec<(String, String)>
 }
 
 
+/// File moved to the trash by sea-serpent
+pub struct TrashedFile {
+    /// Original path of file
+    pub path: PathBuf,
+    /// Name of file in trash
+    pub trash_name: String,
+    /// Tags and attributes of the file and all files below it
+    pub files: Vec<SearchResult>,
+}
+
+
 /// Create full path to sqlite file
 fn create_data_path(database_path: &Path) -> PathBuf {
     database_path.join(DATA_FILE)
 }
 
+/// Set connection options. Sqlite resets these for every new connection.
+fn configure_connection(connection: &mut SqliteConnection) -> Result<(), DatabaseError> {
+    // Required for `ON DELETE CASCADE` to remove tags and attributes with their file
+    connection.batch_execute("PRAGMA foreign_keys = ON;")?;
+    Ok(())
+}
+
+/// Run `f` on `target` inside a transaction of the storage `storage` returns for it.
+/// All changes made by `f` are rolled back if it returns an error.
+pub fn in_transaction<S, T, E, F>(target: &mut S, storage: fn(&mut S) -> &mut DatabaseStorage, f: F) -> Result<T, E>
+where
+    F: FnOnce(&mut S) -> Result<T, E>,
+    E: From<DatabaseError>,
+{
+    storage(target).begin_transaction()?;
+    match f(target) {
+        Ok(value) => {
+            storage(target).commit_transaction()?;
+            Ok(value)
+        },
+        Err(err) => {
+            storage(target).rollback_transaction()?;
+            Err(err)
+        }
+    }
+}
+
+
 impl DatabaseStorage {
 
 
-    /// Load data file from disk
-    pub fn load(database_path: &Path) -> Result<Self, DatabaseError> {
+    /// Load data file from disk.
+    /// `busy_timeout` is the number of milliseconds to wait for other processes to release the database.
+    pub fn load(database_path: &Path, busy_timeout: u64) -> Result<Self, DatabaseError> {
         let data_path = create_data_path(database_path);
         let data_str = data_path.to_str()
             .ok_or(DatabaseError::DatabaseNotFound)?;
         let mut connection = SqliteConnection::establish(&data_str)?;
+        // Write-ahead logging allows reading while another process is writing
+        connection.batch_execute(&format!(
+            "PRAGMA busy_timeout = {busy_timeout}; PRAGMA journal_mode = WAL;"
+        ))?;
         connection.run_pending_migrations(MIGRATIONS)
             .map_err(|_| DatabaseError::DBSetup)?;
-        let data = Self { connection };
+        configure_connection(&mut connection)?;
+        let data = Self {
+            connection,
+            journal: None,
+            journal_marks: Vec::new(),
+        };
         return Ok(data);
     }
 
 
+    /// Run `f` inside a transaction.
+    /// All changes made by `f` are rolled back if it returns an error.
+    pub fn transaction<T, E, F>(&mut self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Self) -> Result<T, E>,
+        E: From<DatabaseError>,
+    {
+        in_transaction(self, |storage| storage, f)
+    }
+
+
+    /// Start new transaction. Nested transactions are created as savepoints.
+    pub fn begin_transaction(&mut self) -> Result<(), DatabaseError> {
+        if self.journal_marks.is_empty() {
+            // Take the write lock right away. A deferred transaction that reads first fails
+            // without waiting for the busy timeout if another process writes in between.
+            AnsiTransactionManager::begin_transaction_sql(&mut self.connection, "BEGIN IMMEDIATE")?;
+        } else {
+            AnsiTransactionManager::begin_transaction(&mut self.connection)?;
+        }
+        self.journal_marks.push(self.journal.as_ref().map_or(0, Vec::len));
+        Ok(())
+    }
+
+
+    /// Commit the innermost transaction
+    pub fn commit_transaction(&mut self) -> Result<(), DatabaseError> {
+        self.journal_marks.pop();
+        AnsiTransactionManager::commit_transaction(&mut self.connection)?;
+        Ok(())
+    }
+
+
+    /// Roll back the innermost transaction.
+    /// Changes recorded for the journal during the transaction are dropped as well.
+    pub fn rollback_transaction(&mut self) -> Result<(), DatabaseError> {
+        let mark = self.journal_marks.pop();
+        if let (Some(journal), Some(mark)) = (&mut self.journal, mark) {
+            journal.truncate(mark);
+        }
+        AnsiTransactionManager::rollback_transaction(&mut self.connection)?;
+        Ok(())
+    }
+
+
     fn get_file_id(&mut self, file: &Path) -> Result<i32, DatabaseError> {
         let path_str = file.to_string_lossy().to_string();
         let file_ids = models::files::table
@@ -87,6 +191,7 @@ impl DatabaseStorage {
                 let new_id: models::File = diesel::insert_into(models::files::table)
                     .values(models::files::path.eq(path_str))
                     .get_result(&mut self.connection)?;
+                self.record(Change::AddFile { path: file.to_path_buf() });
                 Ok(new_id.id)
             },
             x => x
@@ -104,7 +209,10 @@ impl DatabaseStorage {
             .values(&new_tag)
             .execute(&mut self.connection);
         match result {
-            Ok(_) => Ok(()),
+            Ok(_) => {
+                self.record(Change::AddTag { path: file.to_path_buf(), tag: new_tag.tag });
+                Ok(())
+            },
             // Ignore error when trying to add the same tag twice
             Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => Ok(()),
             Err(e) => Err(e)?
@@ -123,7 +231,14 @@ impl DatabaseStorage {
             .values(&new_attribute)
             .execute(&mut self.connection);
         match result {
-            Ok(_) => Ok(()),
+            Ok(_) => {
+                self.record(Change::AddAttribute {
+                    path: file.to_path_buf(),
+                    key: new_attribute.attr_key,
+                    value: new_attribute.attr_value,
+                });
+                Ok(())
+            },
             // Ignore error when trying to add the same tag twice
             Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => Ok(()),
             Err(e) => Err(e)?
@@ -139,29 +254,263 @@ impl DatabaseStorage {
                 let db_tag = models::tags::table
                     .filter(models::tags::file_id.is(file_id))
                     .filter(models::tags::tag.is(key));
-                diesel::delete(db_tag)
+                let removed = diesel::delete(db_tag)
                     .execute(&mut self.connection)?;
+                if removed > 0 {
+                    self.record(Change::RemoveTag { path: file.to_path_buf(), tag: key.clone() });
+                }
             },
             Tag::KeyValue { key, value } => {
                 let db_attribute = models::attributes::table
                     .filter(models::attributes::file_id.is(file_id))
                     .filter(models::attributes::attr_key.is(key))
                     .filter(models::attributes::attr_value.is(value));
-                diesel::delete(db_attribute)
+                let removed = diesel::delete(db_attribute)
                     .execute(&mut self.connection)?;
+                if removed > 0 {
+                    self.record(Change::RemoveAttribute {
+                        path: file.to_path_buf(),
+                        key: key.clone(),
+                        value: value.clone(),
+                    });
+                }
             }
         }
         Ok(())
     }
 
 
-    /// Remove file with all tags and attributes from database
+    /// Remove all values of attribute `key` from file
+    pub fn remove_attribute_key(&mut self, file: &Path, key: &str) -> Result<(), DatabaseError> {
+        let file_id = self.get_file_id(file)?;
+        let values = models::attributes::table
+            .filter(models::attributes::file_id.is(file_id))
+            .filter(models::attributes::attr_key.is(key))
+            .select(models::attributes::attr_value)
+            .load::<String>(&mut self.connection)?;
+        let db_attributes = models::attributes::table
+            .filter(models::attributes::file_id.is(file_id))
+            .filter(models::attributes::attr_key.is(key));
+        diesel::delete(db_attributes)
+            .execute(&mut self.connection)?;
+        for value in values {
+            self.record(Change::RemoveAttribute {
+                path: file.to_path_buf(),
+                key: key.to_string(),
+                value,
+            });
+        }
+        Ok(())
+    }
+
+
+    /// Remove all tags and attributes from file without removing the file from the database
+    pub fn clear_file(&mut self, file: &Path) -> Result<(), DatabaseError> {
+        let file_id = self.get_file_id(file)?;
+        let data = self.get_file(file_id, file.to_path_buf())?;
+        let db_tags = models::tags::table
+            .filter(models::tags::file_id.is(file_id));
+        diesel::delete(db_tags)
+            .execute(&mut self.connection)?;
+        let db_attributes = models::attributes::table
+            .filter(models::attributes::file_id.is(file_id));
+        diesel::delete(db_attributes)
+            .execute(&mut self.connection)?;
+        for tag in data.tags {
+            self.record(Change::RemoveTag { path: data.path.clone(), tag });
+        }
+        for (key, value) in data.attributes {
+            self.record(Change::RemoveAttribute { path: data.path.clone(), key, value });
+        }
+        Ok(())
+    }
+
+
+    /// Remove file with all tags and attributes from database.
+    /// Tags and attributes are removed by the `ON DELETE CASCADE` foreign keys.
     pub fn remove_file(&mut self, file: &Path) -> Result<(), DatabaseError> {
         let file_id = self.get_file_id(file)?;
+        let data = self.get_file(file_id, file.to_path_buf())?;
         let file = models::files::table
             .filter(models::files::id.is(file_id));
         diesel::delete(file)
             .execute(&mut self.connection)?;
+        self.record(Change::RemoveFile { file: data });
+        Ok(())
+    }
+
+
+    /// Move all tags and attributes from `from` to `into` and remove `from` from the database.
+    /// If `into` is not in the database `from` is renamed instead.
+    pub fn merge_file(&mut self, from: &Path, into: &Path) -> Result<(), DatabaseError> {
+        match self.get_file_id(into) {
+            Err(DatabaseError::FileNotFound(_)) => return self.move_file(from, into.to_path_buf()),
+            Err(err) => return Err(err),
+            Ok(_) => (),
+        }
+        self.transaction(|storage| {
+            let data = storage.get_file_from_path(from)?;
+            for tag in &data.tags {
+                storage.add_tag(into, tag)?;
+            }
+            for (key, value) in data.attributes {
+                storage.add_attribute(into, key, value)?;
+            }
+            storage.remove_file(from)
+        })
+    }
+
+
+    /// Returns the number of tags and attributes that belong to files not in the database
+    pub fn count_orphans(&mut self) -> Result<(i64, i64), DatabaseError> {
+        let tags = models::tags::table
+            .filter(models::tags::file_id.ne_all(models::files::table.select(models::files::id)))
+            .count()
+            .get_result::<i64>(&mut self.connection)?;
+        let attributes = models::attributes::table
+            .filter(models::attributes::file_id.ne_all(models::files::table.select(models::files::id)))
+            .count()
+            .get_result::<i64>(&mut self.connection)?;
+        Ok((tags, attributes))
+    }
+
+
+    /// Remove tags and attributes that belong to files not in the database
+    pub fn remove_orphans(&mut self) -> Result<(), DatabaseError> {
+        let tags = models::tags::table
+            .filter(models::tags::file_id.ne_all(models::files::table.select(models::files::id)));
+        diesel::delete(tags)
+            .execute(&mut self.connection)?;
+        let attributes = models::attributes::table
+            .filter(models::attributes::file_id.ne_all(models::files::table.select(models::files::id)));
+        diesel::delete(attributes)
+            .execute(&mut self.connection)?;
+        Ok(())
+    }
+
+
+    /// Run sqlite integrity check. Returns a list of errors.
+    pub fn integrity_check(&mut self) -> Result<Vec<String>, DatabaseError> {
+        let errors = diesel::sql_query("PRAGMA integrity_check")
+            .load::<models::IntegrityCheck>(&mut self.connection)?
+            .into_iter()
+            .map(|row| row.integrity_check)
+            .filter(|message| message != "ok")
+            .collect();
+        Ok(errors)
+    }
+
+
+    /// Returns stored size, modification time, hash and inode of file
+    pub fn get_file_metadata(&mut self, file: &Path) -> Result<FileMetadata, DatabaseError> {
+        let file_id = self.get_file_id(file)?;
+        let (hash, size, mtime, device, inode) = models::files::table
+            .filter(models::files::id.is(file_id))
+            .select((
+                models::files::hash,
+                models::files::size,
+                models::files::mtime,
+                models::files::device,
+                models::files::inode,
+            ))
+            .first::<(Option<String>, Option<i64>, Option<i64>, Option<i64>, Option<i64>)>(&mut self.connection)?;
+        Ok(FileMetadata { hash, size, mtime, device, inode })
+    }
+
+
+    /// Store size, modification time, hash and inode of file
+    pub fn set_file_metadata(&mut self, file: &Path, metadata: &FileMetadata) -> Result<(), DatabaseError> {
+        let file_id = self.get_file_id(file)?;
+        diesel::update(models::files::table)
+            .filter(models::files::id.is(file_id))
+            .set((
+                models::files::hash.eq(&metadata.hash),
+                models::files::size.eq(metadata.size),
+                models::files::mtime.eq(metadata.mtime),
+                models::files::device.eq(metadata.device),
+                models::files::inode.eq(metadata.inode),
+            ))
+            .execute(&mut self.connection)?;
+        Ok(())
+    }
+
+
+    /// Returns paths and stored metadata of all files
+    pub fn get_all_file_metadata(&mut self) -> Result<Vec<(PathBuf, FileMetadata)>, DatabaseError> {
+        let files = models::files::table
+            .select((
+                models::files::path,
+                models::files::hash,
+                models::files::size,
+                models::files::mtime,
+                models::files::device,
+                models::files::inode,
+            ))
+            .load::<(String, Option<String>, Option<i64>, Option<i64>, Option<i64>, Option<i64>)>(&mut self.connection)?
+            .into_iter()
+            .map(|(path, hash, size, mtime, device, inode)| {
+                (PathBuf::from(path), FileMetadata { hash, size, mtime, device, inode })
+            })
+            .collect();
+        Ok(files)
+    }
+
+
+    /// Returns true if `file` is in the database
+    pub fn contains_file(&mut self, file: &Path) -> Result<bool, DatabaseError> {
+        match self.get_file_id(file) {
+            Ok(_) => Ok(true),
+            Err(DatabaseError::FileNotFound(_)) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+
+    /// Returns ids and paths of `path` and all files below it.
+    /// An empty path is the database root.
+    pub fn get_files_below(&mut self, path: &Path) -> Result<Vec<(i32, PathBuf)>, DatabaseError> {
+        if path.as_os_str().is_empty() {
+            let files = self.get_files()?
+                .into_iter()
+                .map(|(file_id, file_path)| (file_id, PathBuf::from(file_path)))
+                .collect();
+            return Ok(files);
+        }
+        let path_str = path.to_string_lossy().to_string();
+        // `LIKE` can match too much, so the result is filtered afterwards
+        let pattern = format!("{}{}%", path_str, std::path::MAIN_SEPARATOR);
+        let files = models::files::table
+            .filter(models::files::path.is(&path_str).or(models::files::path.like(pattern)))
+            .select((models::files::id, models::files::path))
+            .load::<(i32, String)>(&mut self.connection)?
+            .into_iter()
+            .map(|(file_id, file_path)| (file_id, PathBuf::from(file_path)))
+            .filter(|(_, file_path)| file_path.starts_with(path))
+            .collect();
+        Ok(files)
+    }
+
+
+    /// Remove `path` and all files below it from the database
+    pub fn remove_files_below(&mut self, path: &Path) -> Result<(), DatabaseError> {
+        let files = self.get_files_below(path)?;
+        let file_ids: Vec<i32> = files
+            .iter()
+            .map(|(file_id, _)| *file_id)
+            .collect();
+        // Tags and attributes are needed in the journal to restore the files
+        let removed = if self.is_journaling() {
+            files.into_iter()
+                .map(|(file_id, file_path)| self.get_file(file_id, file_path))
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            Vec::new()
+        };
+        diesel::delete(models::files::table.filter(models::files::id.eq_any(file_ids)))
+            .execute(&mut self.connection)?;
+        for file in removed {
+            self.record(Change::RemoveFile { file });
+        }
         Ok(())
     }
 
@@ -187,7 +536,7 @@ impl DatabaseStorage {
 
 
     fn get_file(&mut self, file_id: i32, file_path: PathBuf) -> Result<SearchResult, DatabaseError> {
-        let tags: HashSet<String> = models::tags::table
+        let tags: BTreeSet<String> = models::tags::table
             .filter(models::tags::file_id.is(file_id))
             .select(models::tags::tag)
             .load::<String>(&mut self.connection)?
@@ -195,6 +544,7 @@ impl DatabaseStorage {
             .collect();
         let attributes = models::attributes::table
             .filter(models::attributes::file_id.is(&file_id))
+            .order((models::attributes::attr_key, models::attributes::attr_value))
             .select((models::attributes::attr_key, models::attributes::attr_value))
             .load::<(String, String)>(&mut self.connection)?;
         return Ok(SearchResult {
@@ -223,17 +573,151 @@ impl DatabaseStorage {
 
 
     /// Move all data about `original_path` to `new_path`.
+    /// If `original_path` is a directory all files below it are moved as well.
     /// This will not move the file on disk.
     pub fn move_file(&mut self, original_path: &Path, new_path: PathBuf) -> Result<(), DatabaseError>  {
-        let original_str = original_path.to_string_lossy().to_string();
-        let new_str = new_path.to_string_lossy().to_string();
-        diesel::update(models::files::table)
-            .filter(models::files::path.is(original_str))
-            .set(models::files::path.eq(new_str))
+        self.transaction(|storage| {
+            let files = storage.get_files_below(original_path)?;
+            if files.is_empty() {
+                return Ok(());
+            }
+            for (file_id, path) in files {
+                let moved_path = match rebase_path(&path, original_path, &new_path) {
+                    Some(moved_path) => moved_path,
+                    None => continue,
+                };
+                let moved_str = moved_path.to_string_lossy().to_string();
+                diesel::update(models::files::table)
+                    .filter(models::files::id.is(file_id))
+                    .set(models::files::path.eq(moved_str))
+                    .execute(&mut storage.connection)?;
+            }
+            storage.record(Change::MoveFile { from: original_path.to_path_buf(), to: new_path });
+            Ok(())
+        })
+    }
+
+
+    /// Returns tags and attributes of `path` and all files below it
+    pub fn get_files_below_with_data(&mut self, path: &Path) -> Result<Vec<SearchResult>, DatabaseError> {
+        self.get_files_below(path)?
+            .into_iter()
+            .map(|(file_id, file_path)| self.get_file(file_id, file_path))
+            .collect()
+    }
+
+
+    /// Add file with all its tags and attributes
+    pub fn add_file(&mut self, file: &SearchResult) -> Result<(), DatabaseError> {
+        self.create_file(&file.path)?;
+        for tag in &file.tags {
+            self.add_tag(&file.path, tag)?;
+        }
+        for (key, value) in &file.attributes {
+            self.add_attribute(&file.path, key.clone(), value.clone())?;
+        }
+        Ok(())
+    }
+
+
+    /// Remember file moved to trash
+    pub fn add_trashed_file(&mut self, trashed: &TrashedFile) -> Result<(), DatabaseError> {
+        diesel::insert_into(models::trash::table)
+            .values((
+                models::trash::path.eq(trashed.path.to_string_lossy().to_string()),
+                models::trash::trash_name.eq(&trashed.trash_name),
+                models::trash::files.eq(serde_json::to_string(&trashed.files)?),
+            ))
+            .execute(&mut self.connection)?;
+        Ok(())
+    }
+
+
+    /// Returns the most recently trashed file with original path `path`
+    pub fn get_trashed_file(&mut self, path: &Path) -> Result<TrashedFile, DatabaseError> {
+        let path_str = path.to_string_lossy().to_string();
+        let trashed = models::trash::table
+            .filter(models::trash::path.is(&path_str))
+            .order(models::trash::id.desc())
+            .select((models::trash::path, models::trash::trash_name, models::trash::files))
+            .first::<models::TrashedFile>(&mut self.connection)
+            .optional()?
+            .ok_or_else(|| DatabaseError::FileNotFound(path.to_path_buf()))?;
+        trashed_file_from_model(trashed)
+    }
+
+
+    /// Returns all files moved to trash
+    pub fn get_trashed_files(&mut self) -> Result<Vec<TrashedFile>, DatabaseError> {
+        models::trash::table
+            .order(models::trash::id)
+            .select((models::trash::path, models::trash::trash_name, models::trash::files))
+            .load::<models::TrashedFile>(&mut self.connection)?
+            .into_iter()
+            .map(trashed_file_from_model)
+            .collect()
+    }
+
+
+    /// Forget the most recently trashed file with original path `path`
+    pub fn remove_trashed_file(&mut self, path: &Path) -> Result<(), DatabaseError> {
+        let path_str = path.to_string_lossy().to_string();
+        let trash_id = models::trash::table
+            .filter(models::trash::path.is(&path_str))
+            .order(models::trash::id.desc())
+            .select(models::trash::id)
+            .first::<i32>(&mut self.connection)?;
+        diesel::delete(models::trash::table.filter(models::trash::id.is(trash_id)))
             .execute(&mut self.connection)?;
         Ok(())
     }
 
+
+    /// Copy tags and attributes of `original_path` and all files below it to `new_path`.
+    /// Only tags and attributes with a key accepted by `copy_key` are copied.
+    /// Returns the paths of the new files.
+    pub fn copy_file(&mut self, original_path: &Path, new_path: &Path, copy_key: &dyn Fn(&str) -> bool) -> Result<Vec<PathBuf>, DatabaseError> {
+        self.transaction(|storage| {
+            let mut copied = Vec::new();
+            for (file_id, path) in storage.get_files_below(original_path)? {
+                let copy_path = match rebase_path(&path, original_path, new_path) {
+                    Some(copy_path) => copy_path,
+                    None => continue,
+                };
+                let data = storage.get_file(file_id, path)?;
+                storage.create_file(&copy_path)?;
+                for tag in data.tags.iter().filter(|tag| copy_key(tag)) {
+                    storage.add_tag(&copy_path, tag)?;
+                }
+                for (key, value) in data.attributes.into_iter().filter(|(key, _)| copy_key(key)) {
+                    storage.add_attribute(&copy_path, key, value)?;
+                }
+                copied.push(copy_path);
+            }
+            Ok(copied)
+        })
+    }
+
+}
+
+
+fn trashed_file_from_model(trashed: models::TrashedFile) -> Result<TrashedFile, DatabaseError> {
+    Ok(TrashedFile {
+        path: PathBuf::from(trashed.path),
+        trash_name: trashed.trash_name,
+        files: serde_json::from_str(&trashed.files)?,
+    })
+}
+
+
+/// Replace the `original` prefix of `path` with `new`
+fn rebase_path(path: &Path, original: &Path, new: &Path) -> Option<PathBuf> {
+    let relative = path.strip_prefix(original).ok()?;
+    if relative.as_os_str().is_empty() {
+        Some(new.to_path_buf())
+    } else {
+        Some(new.join(relative))
+    }
 }
 
 
@@ -289,15 +773,20 @@ mod test {
 
     use super::super::Tag;
     use std::str::FromStr;
-    use diesel::Connection;
+    use diesel::{Connection, QueryDsl, RunQueryDsl, connection::SimpleConnection};
     use diesel_migrations::MigrationHarness;
 
     fn create_memory_db() -> super::DatabaseStorage {
         let connection = diesel::sqlite::SqliteConnection::establish(":memory:")
             .unwrap();
-        let mut data = super::DatabaseStorage { connection };
+        let mut data = super::DatabaseStorage {
+            connection,
+            journal: None,
+            journal_marks: Vec::new(),
+        };
         data.connection.revert_all_migrations(super::MIGRATIONS).unwrap();
         data.connection.run_pending_migrations(super::MIGRATIONS).unwrap();
+        super::configure_connection(&mut data.connection).unwrap();
         return data;
     }
 
@@ -324,6 +813,30 @@ mod test {
         assert!(!file_contains(&mut data, &path, &tag));
     }
 
+    #[test]
+    fn remove_attribute_key() {
+        let mut data = create_memory_db();
+        let path = std::path::PathBuf::from_str("test_file").unwrap();
+        data.add_attribute(&path, "key".to_string(), "a".to_string()).unwrap();
+        data.add_attribute(&path, "key".to_string(), "b".to_string()).unwrap();
+        data.add_attribute(&path, "other".to_string(), "c".to_string()).unwrap();
+        data.remove_attribute_key(&path, "key").unwrap();
+        let result = data.get_file_from_path(&path).unwrap();
+        assert_eq!(result.attributes, vec![("other".to_string(), "c".to_string())]);
+    }
+
+    #[test]
+    fn clear_file() {
+        let mut data = create_memory_db();
+        let path = std::path::PathBuf::from_str("test_file").unwrap();
+        data.add_tag(&path, &"test_tag".to_string()).unwrap();
+        data.add_attribute(&path, "key".to_string(), "value".to_string()).unwrap();
+        data.clear_file(&path).unwrap();
+        let result = data.get_file_from_path(&path).unwrap();
+        assert!(result.tags.is_empty());
+        assert!(result.attributes.is_empty());
+    }
+
     #[test]
     fn remove_file() {
         let mut data = create_memory_db();
@@ -332,6 +845,179 @@ mod test {
         data.add_tag(&path, &tag).unwrap();
         data.remove_file(&path).unwrap();
         assert!(data.get_file_from_path(&path).is_err());
+        // Tags should be removed together with the file
+        let tag_count: i64 = super::models::tags::table
+            .count()
+            .get_result(&mut data.connection)
+            .unwrap();
+        assert_eq!(tag_count, 0);
+    }
+
+    #[test]
+    fn migration_removes_orphans() {
+        let mut data = create_memory_db();
+        data.connection.revert_all_migrations(super::MIGRATIONS).unwrap();
+        // Create orphaned tag with the original schema, which didn't enforce foreign keys
+        data.connection.run_next_migration(super::MIGRATIONS).unwrap();
+        data.connection.batch_execute("PRAGMA foreign_keys = OFF;").unwrap();
+        data.connection.batch_execute("INSERT INTO tags (file_id, tag) VALUES (1, 'orphan');").unwrap();
+        data.connection.run_pending_migrations(super::MIGRATIONS).unwrap();
+        let tag_count: i64 = super::models::tags::table
+            .count()
+            .get_result(&mut data.connection)
+            .unwrap();
+        assert_eq!(tag_count, 0);
+    }
+
+    #[test]
+    fn merge_file() {
+        let mut data = create_memory_db();
+        let from = std::path::PathBuf::from_str("./test_file").unwrap();
+        let into = std::path::PathBuf::from_str("test_file").unwrap();
+        data.add_tag(&from, &"tag_a".to_string()).unwrap();
+        data.add_tag(&into, &"tag_b".to_string()).unwrap();
+        data.merge_file(&from, &into).unwrap();
+        assert!(data.get_file_from_path(&from).is_err());
+        assert!(file_contains(&mut data, &into, &"tag_a".to_string()));
+        assert!(file_contains(&mut data, &into, &"tag_b".to_string()));
+    }
+
+    #[test]
+    fn integrity_check() {
+        let mut data = create_memory_db();
+        assert!(data.integrity_check().unwrap().is_empty());
+        assert_eq!(data.count_orphans().unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn rollback_transaction() {
+        let mut data = create_memory_db();
+        let path = std::path::PathBuf::from_str("test_file").unwrap();
+        let result: Result<(), super::DatabaseError> = data.transaction(|data| {
+            data.add_tag(&path, &"test_tag".to_string())?;
+            Err(super::DatabaseError::DBSetup)
+        });
+        assert!(result.is_err());
+        assert!(data.get_file_from_path(&path).is_err());
+    }
+
+    #[test]
+    fn file_metadata() {
+        let mut data = create_memory_db();
+        let path = std::path::PathBuf::from_str("test_file").unwrap();
+        data.add_tag(&path, &"test_tag".to_string()).unwrap();
+        assert_eq!(data.get_file_metadata(&path).unwrap(), Default::default());
+        let metadata = super::FileMetadata {
+            hash: Some("abc".to_string()),
+            size: Some(3),
+            mtime: Some(100),
+            device: Some(1),
+            inode: Some(2),
+        };
+        data.set_file_metadata(&path, &metadata).unwrap();
+        assert_eq!(data.get_file_metadata(&path).unwrap(), metadata);
+    }
+
+    #[test]
+    fn move_directory() {
+        let mut data = create_memory_db();
+        let tag = "test_tag".to_string();
+        for path in ["dir", "dir/file", "dir/sub/file", "directory/file"] {
+            data.add_tag(std::path::Path::new(path), &tag).unwrap();
+        }
+        data.move_file(std::path::Path::new("dir"), std::path::PathBuf::from("new")).unwrap();
+        for path in ["new", "new/file", "new/sub/file", "directory/file"] {
+            assert!(file_contains(&mut data, std::path::Path::new(path), &tag));
+        }
+        assert!(!data.contains_file(std::path::Path::new("dir/file")).unwrap());
+    }
+
+    #[test]
+    fn copy_file() {
+        let mut data = create_memory_db();
+        let original = std::path::PathBuf::from_str("dir/file").unwrap();
+        data.add_tag(&original, &"test_tag".to_string()).unwrap();
+        data.add_attribute(&original, "id".to_string(), "1".to_string()).unwrap();
+        let copied = data.copy_file(
+            std::path::Path::new("dir"),
+            std::path::Path::new("copy"),
+            &|key: &str| key != "id"
+        ).unwrap();
+        let copy = std::path::PathBuf::from_str("copy/file").unwrap();
+        assert_eq!(copied, vec![copy.clone()]);
+        assert!(file_contains(&mut data, &copy, &"test_tag".to_string()));
+        assert!(data.get_file_from_path(&copy).unwrap().attributes.is_empty());
+        assert!(file_contains(&mut data, &original, &"test_tag".to_string()));
+    }
+
+    #[test]
+    fn trashed_file() {
+        let mut data = create_memory_db();
+        let path = std::path::PathBuf::from_str("dir/file").unwrap();
+        data.add_tag(&path, &"test_tag".to_string()).unwrap();
+        let trashed = super::TrashedFile {
+            path: std::path::PathBuf::from("dir"),
+            trash_name: "dir".to_string(),
+            files: data.get_files_below_with_data(std::path::Path::new("dir")).unwrap(),
+        };
+        data.add_trashed_file(&trashed).unwrap();
+        data.remove_files_below(&trashed.path).unwrap();
+        assert!(data.get_file_from_path(&path).is_err());
+        let restored = data.get_trashed_file(&trashed.path).unwrap();
+        for file in &restored.files {
+            data.add_file(file).unwrap();
+        }
+        data.remove_trashed_file(&trashed.path).unwrap();
+        assert!(file_contains(&mut data, &path, &"test_tag".to_string()));
+        assert!(data.get_trashed_files().unwrap().is_empty());
+    }
+
+    #[test]
+    fn undo_journal_entry() {
+        let mut data = create_memory_db();
+        let path = std::path::PathBuf::from_str("test_file").unwrap();
+        data.add_tag(&path, &"kept_tag".to_string()).unwrap();
+        data.start_journal();
+        data.add_tag(&path, &"test_tag".to_string()).unwrap();
+        data.remove_tag(&path, &Tag::new("kept_tag")).unwrap();
+        data.move_file(&path, std::path::PathBuf::from("moved")).unwrap();
+        // Changes that are rolled back should not be recorded
+        let _: Result<(), super::DatabaseError> = data.transaction(|data| {
+            data.add_tag(std::path::Path::new("other_file"), &"test_tag".to_string())?;
+            Err(super::DatabaseError::DBSetup)
+        });
+        data.finish_journal("test").unwrap();
+        let entry = data.get_last_journal_entry().unwrap().unwrap();
+        assert_eq!(entry.operation, "test");
+        assert_eq!(entry.changes, 3);
+        let changes = data.get_journal_changes(entry.id).unwrap();
+        data.undo_journal_entry(entry.id, &changes).unwrap();
+        assert!(file_contains(&mut data, &path, &"kept_tag".to_string()));
+        assert!(!file_contains(&mut data, &path, &"test_tag".to_string()));
+        assert!(data.get_last_journal_entry().unwrap().is_none());
+    }
+
+    #[test]
+    fn file_changes() {
+        let mut data = create_memory_db();
+        let path = std::path::PathBuf::from_str("dir/file").unwrap();
+        data.start_journal();
+        data.add_tag(&path, &"test_tag".to_string()).unwrap();
+        data.finish_journal("add").unwrap();
+        data.start_journal();
+        data.move_file(std::path::Path::new("dir"), std::path::PathBuf::from("new")).unwrap();
+        data.add_tag(std::path::Path::new("new/other"), &"test_tag".to_string()).unwrap();
+        data.finish_journal("mv").unwrap();
+        let changes = data.get_file_changes(std::path::Path::new("new/file")).unwrap();
+        let operations: Vec<_> = changes.iter().map(|change| change.operation.as_str()).collect();
+        assert_eq!(operations, vec!["add", "add", "mv"]);
+        match &changes[2].change {
+            super::Change::MoveFile { from, to } => {
+                assert_eq!(from, &path);
+                assert_eq!(to, std::path::Path::new("new/file"));
+            },
+            _ => panic!("Expected move"),
+        }
     }
 
     #[test]
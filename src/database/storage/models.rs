@@ -10,13 +10,23 @@ pub struct NewFile {
 #[diesel(table_name = files)]
 pub struct File {
     pub id: i32,
-    pub path: String
+    pub path: String,
+    pub hash: Option<String>,
+    pub size: Option<i64>,
+    pub mtime: Option<i64>,
+    pub device: Option<i64>,
+    pub inode: Option<i64>,
 }
 
 diesel::table! {
     files (id) {
         id -> Integer,
         path -> Text,
+        hash -> Nullable<Text>,
+        size -> Nullable<BigInt>,
+        mtime -> Nullable<BigInt>,
+        device -> Nullable<BigInt>,
+        inode -> Nullable<BigInt>,
     }
 }
 
@@ -50,8 +60,84 @@ diesel::table! {
     }
 }
 
+#[derive(Queryable)]
+pub struct TrashedFile {
+    pub path: String,
+    pub trash_name: String,
+    pub files: String,
+}
+
+diesel::table! {
+    trash (id) {
+        id -> Integer,
+        path -> Text,
+        trash_name -> Text,
+        files -> Text,
+    }
+}
+
+#[derive(Queryable)]
+pub struct JournalEntry {
+    pub id: i32,
+    pub operation: String,
+    pub timestamp: i64,
+    pub undone: bool,
+    pub user: Option<String>,
+}
+
+diesel::table! {
+    journal (id) {
+        id -> Integer,
+        operation -> Text,
+        timestamp -> BigInt,
+        undone -> Bool,
+        user -> Nullable<Text>,
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = journal_changes)]
+pub struct JournalChange {
+    pub journal_id: i32,
+    pub position: i32,
+    pub path: String,
+    pub new_path: Option<String>,
+    pub change: String,
+}
+
+diesel::table! {
+    journal_changes (journal_id, position) {
+        journal_id -> Integer,
+        position -> Integer,
+        path -> Text,
+        new_path -> Nullable<Text>,
+        change -> Text,
+    }
+}
+
 joinable!(tags -> files (file_id));
 joinable!(attributes -> files (file_id));
 allow_tables_to_appear_in_same_query!(files, tags);
 allow_tables_to_appear_in_same_query!(files, attributes);
 allow_tables_to_appear_in_same_query!(tags, attributes);
+joinable!(journal_changes -> journal (journal_id));
+allow_tables_to_appear_in_same_query!(journal, journal_changes);
+
+#[derive(QueryableByName)]
+pub struct IntegrityCheck {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub integrity_check: String,
+}
+
+/// Tag of a file in a TMSU database
+#[derive(QueryableByName)]
+pub struct TmsuFileTag {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub directory: String,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub name: String,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub tag: String,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Text>)]
+    pub value: Option<String>,
+}
@@ -0,0 +1,85 @@
+use super::models;
+use crate::database::DatabaseError;
+
+use std::path::{Path, PathBuf};
+use diesel::{
+    prelude::*,
+    sqlite::SqliteConnection,
+};
+
+
+/// Tag or value-tag of a file in a TMSU database
+#[derive(Debug, PartialEq)]
+pub struct TmsuTag {
+    /// Path of the file as stored by TMSU
+    pub path: PathBuf,
+    pub tag: String,
+    /// Value of the tag, if any
+    pub value: Option<String>,
+}
+
+
+/// Read all tags from the TMSU database at `path`
+pub fn read_tmsu_database(path: &Path) -> Result<Vec<TmsuTag>, DatabaseError> {
+    // Connecting to a missing file would create a new database
+    if !path.is_file() {
+        return Err(DatabaseError::FileNotFound(path.to_path_buf()));
+    }
+    let path_str = path.to_str()
+        .ok_or_else(|| DatabaseError::FileNotFound(path.to_path_buf()))?;
+    let mut connection = SqliteConnection::establish(path_str)?;
+    load_tmsu_tags(&mut connection)
+}
+
+
+fn load_tmsu_tags(connection: &mut SqliteConnection) -> Result<Vec<TmsuTag>, DatabaseError> {
+    // Tags without a value have value id 0, which does not exist in the value table
+    let tags = diesel::sql_query(
+        "SELECT file.directory AS directory, file.name AS name, tag.name AS tag, value.name AS value
+        FROM file_tag
+        JOIN file ON file.id = file_tag.file_id
+        JOIN tag ON tag.id = file_tag.tag_id
+        LEFT JOIN value ON value.id = file_tag.value_id
+        ORDER BY file.directory, file.name"
+    )
+        .load::<models::TmsuFileTag>(connection)?
+        .into_iter()
+        .map(|row| TmsuTag {
+            path: Path::new(&row.directory).join(row.name),
+            tag: row.tag,
+            value: row.value,
+        })
+        .collect();
+    Ok(tags)
+}
+
+
+#[cfg(test)]
+mod test {
+
+    use std::path::PathBuf;
+    use diesel::{Connection, connection::SimpleConnection};
+
+    #[test]
+    fn load_tmsu_tags() {
+        let mut connection = diesel::sqlite::SqliteConnection::establish(":memory:").unwrap();
+        connection.batch_execute("
+            CREATE TABLE tag (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+            CREATE TABLE value (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+            CREATE TABLE file (id INTEGER PRIMARY KEY, directory TEXT NOT NULL, name TEXT NOT NULL);
+            CREATE TABLE file_tag (file_id INTEGER NOT NULL, tag_id INTEGER NOT NULL, value_id INTEGER NOT NULL);
+            INSERT INTO tag VALUES (1, 'music'), (2, 'year');
+            INSERT INTO value VALUES (1, '2019');
+            INSERT INTO file VALUES (1, '/home/user/music', 'song.mp3');
+            INSERT INTO file_tag VALUES (1, 1, 0), (1, 2, 1);
+        ").unwrap();
+        let mut tags = super::load_tmsu_tags(&mut connection).unwrap();
+        tags.sort_by(|a, b| a.tag.cmp(&b.tag));
+        let path = PathBuf::from("/home/user/music/song.mp3");
+        assert_eq!(tags, vec![
+            super::TmsuTag { path: path.clone(), tag: "music".to_string(), value: None },
+            super::TmsuTag { path, tag: "year".to_string(), value: Some("2019".to_string()) },
+        ]);
+    }
+
+}
@@ -22,14 +22,10 @@ impl Tag {
 }
 
 /// Parses attribute if possible
+/// Only the first colon separates key and value, so values can contain colons
 fn parse_attribute(tag: &str) -> Option<(String, String)> {
-    if !tag.contains(":") {
-        return None;
-    }
-    let mut iter = tag.split(":");
-    let key = iter.next()?;
-    let value = iter.collect();
-    return Some((key.to_string(), value));
+    let (key, value) = tag.split_once(':')?;
+    return Some((key.to_string(), value.to_string()));
 }
 
 
@@ -67,4 +63,15 @@ mod test {
         }
     }
 
+    #[test]
+    fn parse_value_with_colon() {
+        match super::Tag::new("time:12:30") {
+            super::Tag::KeyValue { key, value } => {
+                assert_eq!("time", key);
+                assert_eq!("12:30", value);
+            },
+            _ => panic!("Should be key-value tag")
+        }
+    }
+
 }
@@ -0,0 +1,82 @@
+use super::{Database, DatabaseError, Export, SearchResult, find, storage, export::EXPORT_VERSION};
+use std::{
+    path::{Path, PathBuf},
+    collections::{BTreeMap, BTreeSet},
+};
+
+/// Name of the directory TMSU stores its database in
+const TMSU_DIR: &str = ".tmsu";
+
+/// Files read from a TMSU database
+pub struct TmsuImport {
+    /// Files inside the database root with paths relative to the root
+    pub export: Export,
+    /// Files outside the database root. These are not part of `export`.
+    pub outside_root: Vec<PathBuf>,
+}
+
+impl Database {
+
+    /// Read all files from the TMSU database file `tmsu_database`.
+    /// Tags with a value become attributes and paths are rebased onto the database root.
+    pub fn read_tmsu(&self, tmsu_database: &Path) -> Result<TmsuImport, DatabaseError> {
+        let root_dir = self.root_dir()?;
+        let tmsu_root = tmsu_root_dir(tmsu_database);
+        let mut files: BTreeMap<PathBuf, SearchResult> = BTreeMap::new();
+        let mut outside_root = BTreeSet::new();
+        for tag in storage::read_tmsu_database(tmsu_database)? {
+            // Absolute paths replace the root when joined
+            let tmsu_path = tmsu_root.join(&tag.path);
+            let path = match find::path_relative_to_db_root(&tmsu_path, root_dir) {
+                Ok(path) => path,
+                Err(DatabaseError::FileNotFound(_)) => {
+                    outside_root.insert(tmsu_path);
+                    continue;
+                },
+                Err(err) => return Err(err),
+            };
+            let file = files.entry(path.clone())
+                .or_insert_with(|| SearchResult {
+                    path,
+                    tags: BTreeSet::new(),
+                    attributes: Vec::new(),
+                });
+            match tag.value {
+                Some(value) => file.attributes.push((tag.tag, value)),
+                None => {
+                    file.tags.insert(tag.tag);
+                },
+            }
+        }
+        Ok(TmsuImport {
+            export: Export {
+                version: EXPORT_VERSION,
+                files: files.into_values().collect(),
+            },
+            outside_root: outside_root.into_iter().collect(),
+        })
+    }
+
+}
+
+/// Returns the directory TMSU stores relative paths relative to,
+/// which is the parent of the `.tmsu` directory containing the database
+fn tmsu_root_dir(tmsu_database: &Path) -> PathBuf {
+    let directory = tmsu_database.parent().unwrap_or(Path::new(""));
+    match directory.file_name() {
+        Some(name) if name == TMSU_DIR => directory.parent().unwrap_or(directory).to_path_buf(),
+        _ => directory.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn tmsu_root_dir() {
+        assert_eq!(super::tmsu_root_dir(Path::new("/home/user/.tmsu/db")), PathBuf::from("/home/user"));
+        assert_eq!(super::tmsu_root_dir(Path::new("/home/user/tmsu.db")), PathBuf::from("/home/user"));
+    }
+
+}
@@ -0,0 +1,67 @@
+use super::{Database, DatabaseError, find, storage::TrashedFile};
+use crate::utils::trash;
+use std::path::{Path, PathBuf};
+use colored::Colorize;
+
+impl Database {
+
+    /// Delete `file` from disk and remove it from the database.
+    /// Directories are deleted together with all their content.
+    pub fn delete_file(&mut self, file: &Path) -> Result<(), DatabaseError> {
+        let relative_path = find::path_relative_to_db_root(file, self.root_dir()?)?;
+        let result = if file.is_dir() {
+            std::fs::remove_dir_all(file)
+        } else {
+            std::fs::remove_file(file)
+        };
+        result.map_err(|_| DatabaseError::WriteToDisk(file.to_path_buf()))?;
+        log::debug!("Removing {} from database", file.to_string_lossy().blue());
+        self.storage.remove_files_below(&relative_path)
+    }
+
+    /// Move `file` to the trash and remove it from the database.
+    /// The tags are kept so they can be restored with `restore_file`.
+    pub fn trash_file(&mut self, file: &Path) -> Result<(), DatabaseError> {
+        let relative_path = find::path_relative_to_db_root(file, self.root_dir()?)?;
+        let files = self.storage.get_files_below_with_data(&relative_path)?;
+        let trash_name = trash::move_to_trash(file)
+            .map_err(|_| DatabaseError::Trash(file.to_path_buf()))?;
+        log::debug!("Moved {} to trash as {}", file.to_string_lossy().blue(), trash_name);
+        let trashed = TrashedFile {
+            path: relative_path,
+            trash_name,
+            files,
+        };
+        self.transaction(|database| {
+            database.storage.add_trashed_file(&trashed)?;
+            database.storage.remove_files_below(&trashed.path)
+        })
+    }
+
+    /// Move `file` back from the trash to its original location and restore its tags
+    pub fn restore_file(&mut self, file: &Path) -> Result<(), DatabaseError> {
+        let relative_path = find::path_relative_to_db_root(file, self.root_dir()?)?;
+        let trashed = self.storage.get_trashed_file(&relative_path)?;
+        if file.exists() {
+            return Err(DatabaseError::DestinationExists(file.to_path_buf()));
+        }
+        trash::restore_from_trash(&trashed.trash_name, file)
+            .map_err(|_| DatabaseError::Trash(file.to_path_buf()))?;
+        self.transaction(|database| {
+            for trashed_file in &trashed.files {
+                database.storage.add_file(trashed_file)?;
+            }
+            database.storage.remove_trashed_file(&trashed.path)
+        })
+    }
+
+    /// Returns the original paths of all files moved to the trash by sea-serpent
+    pub fn trashed_files(&mut self) -> Result<Vec<PathBuf>, DatabaseError> {
+        let paths = self.storage.get_trashed_files()?
+            .into_iter()
+            .map(|trashed| trashed.path)
+            .collect();
+        Ok(paths)
+    }
+
+}
@@ -0,0 +1,23 @@
+use super::ExtractError;
+use lofty::TaggedFileExt;
+use std::path::Path;
+
+/// Read ID3, Vorbis comments and other audio tags
+pub fn extract(path: &Path) -> Result<Vec<(String, String)>, ExtractError> {
+    let tagged_file = lofty::read_from_path(path)
+        .map_err(|err| ExtractError::Metadata(path.to_path_buf(), err.to_string()))?;
+    let mut metadata = Vec::new();
+    for tag in tagged_file.tags() {
+        for item in tag.items() {
+            // Use the key of the tag format, like `TPE1` in ID3 or `ARTIST` in Vorbis comments
+            let key = match item.key().map_key(tag.tag_type(), true) {
+                Some(key) => key.to_string(),
+                None => continue,
+            };
+            if let Some(value) = item.value().text() {
+                metadata.push((key, value.to_string()));
+            }
+        }
+    }
+    Ok(metadata)
+}
@@ -0,0 +1,58 @@
+use super::{Extractor, ExtractError};
+use crate::database::Tag;
+use std::path::Path;
+
+/// Runs an external command with the path of the file as last argument.
+/// Every line the command writes to stdout is parsed like a tag given on the command line,
+/// so `key:value` lines become attributes and other lines become tags.
+pub struct CommandExtractor {
+    program: String,
+    args: Vec<String>,
+}
+
+impl CommandExtractor {
+
+    pub fn new(program: String, args: Vec<String>) -> Self {
+        Self { program, args }
+    }
+
+}
+
+impl Extractor for CommandExtractor {
+
+    fn extract(&self, path: &Path) -> Result<Vec<Tag>, ExtractError> {
+        let output = std::process::Command::new(&self.program)
+            .args(&self.args)
+            .arg(path)
+            .output()
+            .map_err(|err| ExtractError::Command(self.program.clone(), err.to_string()))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            return Err(ExtractError::CommandFailed(self.program.clone(), path.to_path_buf(), stderr));
+        }
+        Ok(parse_output(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+}
+
+fn parse_output(output: &str) -> Vec<Tag> {
+    output.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(Tag::new)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+
+    #[test]
+    fn parse_output() {
+        let tags: Vec<String> = super::parse_output("artist: a\n\ntag_a\ntime:12:30\n")
+            .iter()
+            .map(|tag| tag.to_string())
+            .collect();
+        assert_eq!(tags, vec!["artist: a", "tag_a", "time:12:30"]);
+    }
+
+}
@@ -0,0 +1,16 @@
+use thiserror::Error;
+use displaydoc::Display;
+use std::path::PathBuf;
+
+#[derive(Debug, Error, Display)]
+/// Error reading metadata from file
+pub enum ExtractError {
+    /// Can't read {0} from disk
+    ReadFromDisk(PathBuf),
+    /// Can't read metadata from {0}: {1}
+    Metadata(PathBuf, String),
+    /// Can't run extractor {0}: {1}
+    Command(String, String),
+    /// Extractor {0} failed for {1}: {2}
+    CommandFailed(String, PathBuf, String),
+}
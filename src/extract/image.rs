@@ -0,0 +1,31 @@
+use super::ExtractError;
+use std::path::Path;
+
+/// Read exif data from the primary image
+pub fn extract(path: &Path) -> Result<Vec<(String, String)>, ExtractError> {
+    let file = std::fs::File::open(path)
+        .map_err(|_| ExtractError::ReadFromDisk(path.to_path_buf()))?;
+    let mut reader = std::io::BufReader::new(file);
+    let exif = match exif::Reader::new().read_from_container(&mut reader) {
+        Ok(exif) => exif,
+        // Images without exif data have no metadata
+        Err(exif::Error::NotFound(_)) => return Ok(Vec::new()),
+        Err(err) => return Err(ExtractError::Metadata(path.to_path_buf(), err.to_string())),
+    };
+    let metadata = exif.fields()
+        .filter(|field| field.ifd_num == exif::In::PRIMARY)
+        .map(|field| (field.tag.to_string(), field_value(field)))
+        .collect();
+    Ok(metadata)
+}
+
+fn field_value(field: &exif::Field) -> String {
+    match &field.value {
+        // `display_value` would put text in quotes
+        exif::Value::Ascii(values) => values.iter()
+            .map(|value| String::from_utf8_lossy(value).trim_end_matches('\0').to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => field.display_value().to_string(),
+    }
+}
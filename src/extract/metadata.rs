@@ -0,0 +1,40 @@
+use super::{Extractor, ExtractError};
+use crate::database::Tag;
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+/// File formats understood by the built-in extractors
+#[derive(Debug, Clone, Copy)]
+pub enum MetadataFormat {
+    /// ID3, Vorbis comments and other audio tags
+    Audio,
+    /// Exif data in images
+    Exif,
+    /// Pdf document info
+    Pdf,
+}
+
+/// Built-in extractor for metadata embedded in audio, image and pdf files.
+/// Metadata keys, like `TPE1` in ID3, are renamed with `keys` and metadata without a key in `keys` is skipped.
+pub struct MetadataExtractor {
+    pub format: MetadataFormat,
+    pub keys: Arc<HashMap<String, String>>,
+}
+
+impl Extractor for MetadataExtractor {
+
+    fn extract(&self, path: &Path) -> Result<Vec<Tag>, ExtractError> {
+        let metadata = match self.format {
+            MetadataFormat::Audio => super::audio::extract(path)?,
+            MetadataFormat::Exif => super::image::extract(path)?,
+            MetadataFormat::Pdf => super::pdf::extract(path)?,
+        };
+        let attributes = metadata.into_iter()
+            .filter_map(|(metadata_key, value)| {
+                let key = self.keys.get(&metadata_key)?;
+                Some(Tag::KeyValue { key: key.clone(), value })
+            })
+            .collect();
+        Ok(attributes)
+    }
+
+}
@@ -0,0 +1,24 @@
+#[cfg(feature = "extract")]
+mod audio;
+mod command;
+mod error;
+#[cfg(feature = "extract")]
+mod image;
+#[cfg(feature = "extract")]
+mod metadata;
+#[cfg(feature = "extract")]
+mod pdf;
+mod registry;
+
+pub use command::CommandExtractor;
+pub use error::ExtractError;
+pub use registry::Extractors;
+
+use crate::database::Tag;
+use std::path::Path;
+
+/// Reads tags and attributes from the content of files
+pub trait Extractor {
+    /// Returns the tags and attributes found in `path`
+    fn extract(&self, path: &Path) -> Result<Vec<Tag>, ExtractError>;
+}
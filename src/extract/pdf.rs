@@ -0,0 +1,48 @@
+use super::ExtractError;
+use lopdf::Object;
+use std::path::Path;
+
+/// Read the document info dictionary
+pub fn extract(path: &Path) -> Result<Vec<(String, String)>, ExtractError> {
+    let document = lopdf::Document::load(path)
+        .map_err(|err| ExtractError::Metadata(path.to_path_buf(), err.to_string()))?;
+    let info = match document.trailer.get(b"Info") {
+        Ok(Object::Reference(id)) => document.get_dictionary(*id)
+            .map_err(|err| ExtractError::Metadata(path.to_path_buf(), err.to_string()))?,
+        Ok(Object::Dictionary(info)) => info,
+        // Documents without document info have no metadata
+        _ => return Ok(Vec::new()),
+    };
+    let metadata = info.iter()
+        .filter_map(|(key, value)| match value {
+            Object::String(bytes, _) => Some((String::from_utf8_lossy(key).into_owned(), decode_text_string(bytes))),
+            _ => None,
+        })
+        .collect();
+    Ok(metadata)
+}
+
+/// Decode a pdf text string, which is either UTF-16BE starting with a byte order mark or PDFDocEncoding
+fn decode_text_string(bytes: &[u8]) -> String {
+    match bytes.strip_prefix(&[0xFE, 0xFF]) {
+        Some(utf16) => {
+            let units: Vec<u16> = utf16.chunks_exact(2)
+                .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        },
+        // PDFDocEncoding matches Latin-1 for printable characters
+        None => bytes.iter().map(|&byte| byte as char).collect(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    #[test]
+    fn decode_text_string() {
+        assert_eq!(super::decode_text_string(b"Title"), "Title");
+        assert_eq!(super::decode_text_string(&[0xFE, 0xFF, 0x00, 0x54, 0x00, 0xE9]), "T\u{e9}");
+    }
+
+}
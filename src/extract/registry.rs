@@ -0,0 +1,126 @@
+use super::{Extractor, ExtractError};
+use crate::database::Tag;
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+/// Extractors registered by file extension or MIME type
+#[derive(Default, Clone)]
+pub struct Extractors {
+    by_extension: HashMap<String, Arc<dyn Extractor>>,
+    by_mime_type: HashMap<String, Arc<dyn Extractor>>,
+}
+
+impl Extractors {
+
+    /// Registry without any extractors
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry with the built-in extractors for audio, image and pdf files.
+    /// Metadata keys are renamed to attribute keys with `keys` and metadata without a key in `keys` is skipped.
+    #[cfg(feature = "extract")]
+    pub fn builtin(keys: HashMap<String, String>) -> Self {
+        use super::metadata::{MetadataExtractor, MetadataFormat};
+        let keys = Arc::new(keys);
+        let extractor = |format| -> Arc<dyn Extractor> {
+            Arc::new(MetadataExtractor { format, keys: keys.clone() })
+        };
+        let mut extractors = Self::new();
+        let audio = extractor(MetadataFormat::Audio);
+        extractors.register_mime_type("audio/*", audio.clone());
+        // These are either guessed as video or have no known MIME type
+        for extension in ["mp4", "ape", "wv"] {
+            extractors.register_extension(extension, audio.clone());
+        }
+        for mime_type in ["image/jpeg", "image/tiff", "image/png", "image/webp", "image/heic"] {
+            extractors.register_mime_type(mime_type, extractor(MetadataFormat::Exif));
+        }
+        extractors.register_mime_type("application/pdf", extractor(MetadataFormat::Pdf));
+        extractors
+    }
+
+    /// Registry without any extractors, since built-in extractors are disabled
+    #[cfg(not(feature = "extract"))]
+    pub fn builtin(_keys: HashMap<String, String>) -> Self {
+        Self::new()
+    }
+
+    /// Use `extractor` for files with `extension`, ignoring case.
+    /// Replaces the extractor registered for the extension before.
+    pub fn register_extension(&mut self, extension: &str, extractor: Arc<dyn Extractor>) {
+        self.by_extension.insert(extension.to_lowercase(), extractor);
+    }
+
+    /// Use `extractor` for files of `mime_type`, guessed from their extension.
+    /// `type/*` matches all subtypes. Replaces the extractor registered for the MIME type before.
+    pub fn register_mime_type(&mut self, mime_type: &str, extractor: Arc<dyn Extractor>) {
+        self.by_mime_type.insert(mime_type.to_lowercase(), extractor);
+    }
+
+    /// Returns the extractor for `path`. Extractors registered by extension take precedence
+    /// over extractors registered by exact MIME type, which take precedence over `type/*`.
+    pub fn get(&self, path: &Path) -> Option<&dyn Extractor> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        if let Some(extractor) = self.by_extension.get(&extension) {
+            return Some(extractor.as_ref());
+        }
+        let mime_type = mime_guess::from_ext(&extension).first()?;
+        self.by_mime_type.get(mime_type.essence_str())
+            .or_else(|| self.by_mime_type.get(&format!("{}/*", mime_type.type_())))
+            .map(|extractor| extractor.as_ref())
+    }
+
+    /// Returns the tags and attributes found in `path`. Files without an extractor have none.
+    pub fn extract(&self, path: &Path) -> Result<Vec<Tag>, ExtractError> {
+        match self.get(path) {
+            Some(extractor) => extractor.extract(path),
+            None => Ok(Vec::new()),
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Extractor, Extractors, ExtractError, Tag};
+    use std::{path::Path, sync::Arc};
+
+    struct Fixed(&'static str);
+
+    impl Extractor for Fixed {
+        fn extract(&self, _path: &Path) -> Result<Vec<Tag>, ExtractError> {
+            Ok(vec![Tag::new(self.0)])
+        }
+    }
+
+    fn extracted(extractors: &Extractors, path: &str) -> Vec<String> {
+        extractors.extract(Path::new(path))
+            .unwrap()
+            .iter()
+            .map(|tag| tag.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn lookup() {
+        let mut extractors = Extractors::new();
+        extractors.register_mime_type("audio/*", Arc::new(Fixed("audio")));
+        extractors.register_mime_type("audio/mpeg", Arc::new(Fixed("mpeg")));
+        extractors.register_extension("FLAC", Arc::new(Fixed("flac")));
+        assert_eq!(extracted(&extractors, "a.ogg"), vec!["audio"]);
+        assert_eq!(extracted(&extractors, "a.mp3"), vec!["mpeg"]);
+        assert_eq!(extracted(&extractors, "a.flac"), vec!["flac"]);
+        assert!(extracted(&extractors, "a.txt").is_empty());
+        assert!(extracted(&extractors, "a").is_empty());
+    }
+
+    #[cfg(feature = "extract")]
+    #[test]
+    fn builtin_audio_extensions() {
+        let extractors = Extractors::builtin(Default::default());
+        for file in ["a.mp3", "a.flac", "a.ogg", "a.opus", "a.m4a", "a.wav", "a.aiff", "a.mp4", "a.ape", "a.wv", "a.jpg", "a.heic", "a.pdf"] {
+            assert!(extractors.get(Path::new(file)).is_some(), "{file}");
+        }
+    }
+
+}
@@ -5,10 +5,11 @@ use std::{
     collections::HashMap,
 };
 
-/// Format result based on template
+/// Format result based on template.
+/// Fails if the template is invalid or uses attributes the result doesn't have.
 pub fn format_result(result: &SearchResult, template: &str) -> Result<String, ()> {
     let named_options = formatting_options(result);
-    let args = ParsedFormat::parse(template, &[], &named_options).unwrap();
+    let args = ParsedFormat::parse(template, &[], &named_options).map_err(|_| ())?;
     Ok(format!("{}", args))
 }
 
@@ -42,7 +43,12 @@ impl<'a> FormatArgument for FormatValue<'a> {
     }
 
     fn fmt_display(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Display::fmt(self.0, f)
+        // Padding like `{track:02}` only works on numbers. Without a width the value is kept
+        // as it is, so leading zeros in it aren't lost.
+        match self.0.parse::<usize>() {
+            Ok(number) if f.width().is_some() => fmt::Display::fmt(&number, f),
+            _ => fmt::Display::fmt(self.0, f),
+        }
     }
 
     fn fmt_debug(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -74,3 +80,34 @@ impl<'a> FormatArgument for FormatValue<'a> {
     }
 
 }
+
+#[cfg(test)]
+mod test {
+    use crate::database::SearchResult;
+    use std::path::PathBuf;
+
+    fn result(attributes: &[(&str, &str)]) -> SearchResult {
+        SearchResult {
+            path: PathBuf::from("file"),
+            tags: Default::default(),
+            attributes: attributes.iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn pad_numbers() {
+        let result = result(&[("track", "3"), ("disc", "01"), ("title", "T")]);
+        assert_eq!(super::format_result(&result, "{track:02} {title}"), Ok("03 T".to_string()));
+        assert_eq!(super::format_result(&result, "{track} {disc}"), Ok("3 01".to_string()));
+        assert_eq!(super::format_result(&result, "{title:>3}"), Ok("  T".to_string()));
+    }
+
+    #[test]
+    fn missing_attribute() {
+        let result = result(&[("title", "T")]);
+        assert!(super::format_result(&result, "{artist} - {title}").is_err());
+    }
+
+}
@@ -1,4 +1,6 @@
 pub mod database;
+pub mod extract;
 pub mod format;
 pub mod search;
+pub mod template;
 pub mod utils;
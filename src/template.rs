@@ -0,0 +1,270 @@
+use thiserror::Error;
+use displaydoc::Display;
+use std::{path::Path, str::FromStr};
+
+/// Name of the field whose text is added as a tag instead of as an attribute value
+pub const TAG_FIELD: &str = "#";
+
+#[derive(Debug, Error, Display)]
+/// Error parsing template
+pub enum TemplateError {
+    /// Missing }} after {{ in template {0}
+    UnclosedField(String),
+    /// Unexpected }} in template {0}. Use }}}} for a literal brace
+    UnmatchedBrace(String),
+    /// Field without name in template {0}
+    EmptyField(String),
+}
+
+#[derive(Debug, PartialEq)]
+enum TemplatePart {
+    Literal(String),
+    Field(String),
+    /// `*`, matches like a field but is left out of the result
+    Wildcard,
+}
+
+/// Template like `{artist} - {title}` used to parse attributes out of text.
+/// This is the inverse of `format::format_result`, which fills a template with attributes.
+#[derive(Debug)]
+pub struct Template {
+    parts: Vec<TemplatePart>,
+}
+
+impl Template {
+
+    /// Parse template. Format specifiers like the `02` in `{track:02}` are accepted and ignored,
+    /// so templates used for renaming can be reused. `{{` and `}}` are literal braces and `*` matches any text.
+    pub fn parse(template: &str) -> Result<Self, TemplateError> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                },
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                },
+                '{' => {
+                    let mut field = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => field.push(c),
+                            None => return Err(TemplateError::UnclosedField(template.to_string())),
+                        }
+                    }
+                    let name = field.split(':').next().unwrap_or_default().trim();
+                    if name.is_empty() {
+                        return Err(TemplateError::EmptyField(template.to_string()));
+                    }
+                    if !literal.is_empty() {
+                        parts.push(TemplatePart::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(TemplatePart::Field(name.to_string()));
+                },
+                '}' => return Err(TemplateError::UnmatchedBrace(template.to_string())),
+                '*' => {
+                    if !literal.is_empty() {
+                        parts.push(TemplatePart::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(TemplatePart::Wildcard);
+                },
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(TemplatePart::Literal(literal));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Match all of `text` against the template and return the field names with the text they matched,
+    /// in template order. Fields match at least one character and as few as possible, so in
+    /// `A - B - C` the template `{artist} - {title}` matches `A` as artist.
+    /// Returns `None` if `text` doesn't match.
+    pub fn match_text(&self, text: &str) -> Option<Vec<(String, String)>> {
+        let mut values = Vec::new();
+        if !match_parts(&self.parts, text, &mut values) {
+            return None;
+        }
+        let fields = self.parts.iter()
+            .filter_map(|part| match part {
+                TemplatePart::Field(name) => Some(name.clone()),
+                TemplatePart::Literal(_) | TemplatePart::Wildcard => None,
+            })
+            .zip(values.into_iter().map(str::to_string))
+            .collect();
+        Some(fields)
+    }
+
+}
+
+impl FromStr for Template {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Match `text` against `parts` with backtracking, pushing the text matched by every field to `values`
+fn match_parts<'a>(parts: &[TemplatePart], text: &'a str, values: &mut Vec<&'a str>) -> bool {
+    match parts.split_first() {
+        None => text.is_empty(),
+        Some((TemplatePart::Literal(literal), rest)) => match text.strip_prefix(literal.as_str()) {
+            Some(remaining) => match_parts(rest, remaining, values),
+            None => false,
+        },
+        Some((part, rest)) => {
+            let captured = matches!(part, TemplatePart::Field(_));
+            for end in text.char_indices().map(|(index, c)| index + c.len_utf8()) {
+                if captured {
+                    values.push(&text[..end]);
+                }
+                if match_parts(rest, &text[end..], values) {
+                    return true;
+                }
+                if captured {
+                    values.pop();
+                }
+            }
+            false
+        },
+    }
+}
+
+enum PatternComponent {
+    /// `**`, matches any number of path components
+    Any,
+    Template(Template),
+}
+
+/// Pattern like `photos/{year}/{place}/*` used to parse attributes out of paths.
+/// Every path component is matched against a `Template` and `**` matches any number of components.
+pub struct PathPattern {
+    components: Vec<PatternComponent>,
+}
+
+impl PathPattern {
+
+    pub fn parse(pattern: &str) -> Result<Self, TemplateError> {
+        let components = pattern.split('/')
+            .filter(|component| !component.is_empty() && *component != ".")
+            .map(|component| match component {
+                "**" => Ok(PatternComponent::Any),
+                component => Template::parse(component).map(PatternComponent::Template),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { components })
+    }
+
+    /// Match all of relative `path` against the pattern and return the field names with the text
+    /// they matched, in pattern order. Returns `None` if `path` doesn't match.
+    pub fn match_path(&self, path: &Path) -> Option<Vec<(String, String)>> {
+        let components = path.iter()
+            .map(|component| component.to_str())
+            .collect::<Option<Vec<_>>>()?;
+        let mut fields = Vec::new();
+        match_components(&self.components, &components, &mut fields)
+            .then_some(fields)
+    }
+
+}
+
+impl FromStr for PathPattern {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+fn match_components(patterns: &[PatternComponent], components: &[&str], fields: &mut Vec<(String, String)>) -> bool {
+    match patterns.split_first() {
+        None => components.is_empty(),
+        Some((PatternComponent::Any, rest)) => (0..=components.len())
+            .any(|skipped| match_components(rest, &components[skipped..], fields)),
+        Some((PatternComponent::Template(template), rest)) => {
+            let (component, remaining) = match components.split_first() {
+                Some(split) => split,
+                None => return false,
+            };
+            let matched = match template.match_text(component) {
+                Some(matched) => matched,
+                None => return false,
+            };
+            let length = fields.len();
+            fields.extend(matched);
+            if match_components(rest, remaining, fields) {
+                return true;
+            }
+            fields.truncate(length);
+            false
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Template;
+    use std::path::Path;
+
+    fn fields(template: &str, text: &str) -> Option<Vec<(String, String)>> {
+        Template::parse(template).unwrap().match_text(text)
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Option<Vec<(String, String)>> {
+        Some(pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect())
+    }
+
+    #[test]
+    fn match_text() {
+        assert_eq!(
+            fields("{artist} - {title} ({year})", "A - B - C (2019)"),
+            pairs(&[("artist", "A"), ("title", "B - C"), ("year", "2019")])
+        );
+        assert_eq!(
+            fields("{track:02} {title}", "03 Fjöll"),
+            pairs(&[("track", "03"), ("title", "Fjöll")])
+        );
+        assert_eq!(fields("{{{title}}}", "{x}"), pairs(&[("title", "x")]));
+        assert_eq!(fields("{artist} - {title}", "A-B"), None);
+        assert_eq!(fields("{artist} - {title}", "A - "), None);
+    }
+
+    #[test]
+    fn wildcards() {
+        assert_eq!(fields("* - {title}", "A - B"), pairs(&[("title", "B")]));
+        assert_eq!(fields("{title}*", "A"), None);
+    }
+
+    #[test]
+    fn match_path() {
+        let pattern = super::PathPattern::parse("photos/{year}/{place}/*").unwrap();
+        assert_eq!(
+            pattern.match_path(Path::new("photos/2019/iceland/x.jpg")),
+            pairs(&[("year", "2019"), ("place", "iceland")])
+        );
+        assert_eq!(pattern.match_path(Path::new("photos/2019/x.jpg")), None);
+        let pattern = super::PathPattern::parse("**/{#}/*.jpg").unwrap();
+        assert_eq!(
+            pattern.match_path(Path::new("photos/2019/iceland/x.jpg")),
+            pairs(&[("#", "iceland")])
+        );
+        assert_eq!(pattern.match_path(Path::new("photos/2019/iceland/x.png")), None);
+    }
+
+    #[test]
+    fn invalid_templates() {
+        assert!(Template::parse("{artist").is_err());
+        assert!(Template::parse("artist}").is_err());
+        assert!(Template::parse("{} - {title}").is_err());
+        assert!(Template::parse("{:02}").is_err());
+    }
+
+}
@@ -63,3 +63,17 @@ fn add_files_from_dir(path: &Path, list: &mut Vec<PathBuf>) {
         }
     }
 }
+
+/// Copy file or directory with all its content from `from` to `to`
+pub fn copy_recursive(from: &Path, to: &Path) -> std::io::Result<()> {
+    if from.is_dir() {
+        std::fs::create_dir_all(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        std::fs::copy(from, to).map(|_| ())
+    }
+}
@@ -0,0 +1,130 @@
+//! Trash following the freedesktop.org trash specification
+//! <https://specifications.freedesktop.org/trash-spec/trashspec-latest.html>
+
+use std::{
+    io::{self, Write},
+    fs::{File, OpenOptions},
+    path::{Path, PathBuf},
+};
+
+/// Returns the home trash directory
+pub fn trash_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .map(|data_dir| data_dir.join("Trash"))
+}
+
+fn trash_dir_or_error() -> io::Result<PathBuf> {
+    trash_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Could not find trash directory"))
+}
+
+/// Move `path` to the trash. Returns the name of the file in the trash.
+pub fn move_to_trash(path: &Path) -> io::Result<String> {
+    let trash = trash_dir_or_error()?;
+    let files_dir = trash.join("files");
+    let info_dir = trash.join("info");
+    std::fs::create_dir_all(&files_dir)?;
+    std::fs::create_dir_all(&info_dir)?;
+    // Only the parent is canonicalized, so symlinks are trashed instead of their targets
+    let file_name = path.file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Path has no file name"))?;
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let absolute_path = std::fs::canonicalize(parent)?.join(file_name);
+    let file_name = file_name.to_string_lossy().to_string();
+    let (name, mut info_file) = create_info_file(&files_dir, &info_dir, &file_name)?;
+    let deletion_date = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S");
+    let result = write!(
+        info_file,
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        percent_encode(&absolute_path),
+        deletion_date
+    ).and_then(|_| move_path(&absolute_path, &files_dir.join(&name)));
+    if let Err(err) = result {
+        std::fs::remove_file(info_file_path(&info_dir, &name))?;
+        return Err(err);
+    }
+    Ok(name)
+}
+
+/// Move file called `name` out of the trash to `destination`
+pub fn restore_from_trash(name: &str, destination: &Path) -> io::Result<()> {
+    let trash = trash_dir_or_error()?;
+    if let Some(parent) = destination.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    move_path(&trash.join("files").join(name), destination)?;
+    std::fs::remove_file(info_file_path(&trash.join("info"), name))
+}
+
+fn info_file_path(info_dir: &Path, name: &str) -> PathBuf {
+    info_dir.join(format!("{name}.trashinfo"))
+}
+
+/// Create info file with a name not used by any other file in the trash.
+/// Creating the info file reserves the name.
+fn create_info_file(files_dir: &Path, info_dir: &Path, file_name: &str) -> io::Result<(String, File)> {
+    let mut counter = 0;
+    loop {
+        let name = match counter {
+            0 => file_name.to_string(),
+            _ => format!("{file_name}.{counter}"),
+        };
+        counter += 1;
+        if files_dir.join(&name).exists() {
+            continue;
+        }
+        let result = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(info_file_path(info_dir, &name));
+        match result {
+            Ok(file) => return Ok((name, file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Move file or directory. Falls back to copying if `from` and `to` are on different filesystems.
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    super::files::copy_recursive(from, to)?;
+    if from.is_dir() {
+        std::fs::remove_dir_all(from)
+    } else {
+        std::fs::remove_file(from)
+    }
+}
+
+/// Percent-encode path for use in an info file
+fn percent_encode(path: &Path) -> String {
+    let mut output = String::new();
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                output.push(byte as char)
+            },
+            _ => output.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    #[test]
+    fn percent_encode() {
+        assert_eq!(super::percent_encode(Path::new("/home/a b/æ.txt")), "/home/a%20b/%C3%A6.txt");
+    }
+
+}